@@ -0,0 +1,210 @@
+use chrono::{DateTime, Utc};
+
+use crate::config::{Configuration, TIME_REPORT_PARTS};
+use crate::message_context::{is_known_context, CONTEXTS};
+use crate::replies::{Replies, Reply};
+use crate::storage::{Storage, StoredMessage};
+use crate::template::{get_unknown_variables, Variables, REMINDER_VARIABLES};
+
+// Slack turns straight quotes into curly ones unless the user turns it off
+const QUOTES: [char; 3] = ['"', '“', '”'];
+
+/// Answers `admin ...`, e.g. `admin meddelande lägg-till middle 12 "God jul!"`. Only the users
+/// listed under `admins` in config.yaml may use it.
+pub async fn admin_message(
+    config: &Configuration,
+    replies: &Replies<'_>,
+    storage: &Storage,
+    user: &str,
+    arguments: &[&str],
+    now: DateTime<Utc>,
+) -> String {
+    if !config.is_admin(user) {
+        return replies.get(Reply::AdminOnly, &Variables::default().with_user(user));
+    }
+
+    match arguments {
+        ["meddelande" | "message", "lägg-till" | "add", part, context, text @ ..] => {
+            add_message(replies, storage, user, part, context, &text.join(" "), now).await
+        }
+        ["meddelande" | "message", "lista" | "list"] => list_messages(replies, storage).await,
+        ["meddelande" | "message", "ta-bort" | "remove", id] => {
+            let removed = match id.parse::<u32>() {
+                Ok(id) => {
+                    storage
+                        .update(|data| data.remove_message(id, user, now))
+                        .await
+                }
+                Err(_) => Ok(None),
+            };
+            match removed {
+                Ok(Some(message)) => replies.get(Reply::AdminMessageRemoved, &describe(&message)),
+                Ok(None) => replies.get(
+                    Reply::AdminMessageNotFound,
+                    &Variables::default().with("id", id.to_string()),
+                ),
+                Err(error) => {
+                    println!("failed to remove message: {}", error);
+                    replies.get(Reply::Error, &Variables::default())
+                }
+            }
+        }
+        _ => replies.get(Reply::AdminUsage, &Variables::default()),
+    }
+}
+
+async fn add_message(
+    replies: &Replies<'_>,
+    storage: &Storage,
+    user: &str,
+    part: &str,
+    context: &str,
+    text: &str,
+    now: DateTime<Utc>,
+) -> String {
+    if !TIME_REPORT_PARTS.contains(&part) {
+        let variables = Variables::default().with("name", part.to_string());
+        return replies.get(Reply::AdminMessageInvalidPart, &variables);
+    }
+    if !is_known_context(context) {
+        let variables = Variables::default()
+            .with("name", context.to_string())
+            .with("contexts", CONTEXTS.join(", "));
+        return replies.get(Reply::AdminMessageInvalidContext, &variables);
+    }
+
+    let text = text.trim_matches(|c: char| QUOTES.contains(&c) || c.is_whitespace());
+    if text.is_empty() {
+        return replies.get(Reply::AdminMessageEmpty, &Variables::default());
+    }
+    if let Some(name) = get_unknown_variables(text, &REMINDER_VARIABLES).first() {
+        let variables = Variables::default().with("name", format!("{{{{{}}}}}", name));
+        return replies.get(Reply::AdminMessageUnknownVariables, &variables);
+    }
+
+    match storage
+        .update(|data| data.add_message(part, context, text, user, now))
+        .await
+    {
+        Ok(message) => replies.get(Reply::AdminMessageAdded, &describe(&message)),
+        Err(error) => {
+            println!("failed to store message: {}", error);
+            replies.get(Reply::Error, &Variables::default())
+        }
+    }
+}
+
+async fn list_messages(replies: &Replies<'_>, storage: &Storage) -> String {
+    let messages = storage.get_messages().await;
+    if messages.is_empty() {
+        return replies.get(Reply::AdminMessageListEmpty, &Variables::default());
+    }
+
+    let mut lines = vec![replies.get(Reply::AdminMessageList, &Variables::default())];
+    for message in &messages {
+        let variables = describe(message)
+            .with("text", message.text.clone())
+            .with_user(&message.added_by);
+        lines.push(replies.get(Reply::AdminMessageListItem, &variables));
+    }
+    lines.join("\n")
+}
+
+fn describe(message: &StoredMessage) -> Variables {
+    Variables::default()
+        .with("id", message.id.to_string())
+        .with("part", message.part.clone())
+        .with("context", message.context.clone())
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+    use std::path::Path;
+
+    use super::admin_message;
+    use crate::config::Configuration;
+    use crate::locale::Locale;
+    use crate::storage::Storage;
+
+    fn config() -> Configuration {
+        let mut value: serde_yaml::Value =
+            serde_yaml::from_str(&std::fs::read_to_string(Path::new("config.yaml")).unwrap())
+                .expect("failed");
+        value["admins"] = serde_yaml::from_str("[U1]").expect("failed");
+        Configuration::parse_value(value).expect("failed")
+    }
+
+    fn storage(name: &str) -> (Storage, std::path::PathBuf) {
+        let path = std::env::temp_dir().join(format!(
+            "joel-bot-admin-{}-{}.json",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        (Storage::open_path(path.clone()).expect("failed"), path)
+    }
+
+    #[tokio::test]
+    async fn only_admins_may_change_messages() {
+        let config = config();
+        let replies = config.get_replies(Locale::Sv);
+        let (storage, path) = storage("only-admins");
+
+        let answer = admin_message(
+            &config,
+            &replies,
+            &storage,
+            "U2",
+            &["meddelande", "lägg-till", "middle", "12", "\"Hej\""],
+            Utc::now(),
+        )
+        .await;
+
+        assert!(answer.contains("<@U2>"));
+        assert!(storage.get_messages().await.is_empty());
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn messages_are_added_listed_and_removed() {
+        let config = config();
+        let replies = config.get_replies(Locale::Sv);
+        let (storage, path) = storage("add-list-remove");
+        let admin = async |arguments: &[&str]| {
+            admin_message(&config, &replies, &storage, "U1", arguments, Utc::now()).await
+        };
+
+        admin(&[
+            "meddelande",
+            "lägg-till",
+            "middle",
+            "12",
+            "“God",
+            "jul,",
+            "{{month_name}}!”",
+        ])
+        .await;
+        admin(&["message", "add", "end", "påsk", "\"{{okänd}}\""]).await;
+        admin(&["message", "add", "slutet", "general", "\"Hej\""]).await;
+        admin(&["message", "add", "end", "13", "\"Hej\""]).await;
+
+        let messages = storage.get_messages().await;
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].text, "God jul, {{month_name}}!");
+        assert_eq!(messages[0].context, "12");
+        assert_eq!(messages[0].added_by, "U1");
+        assert!(admin(&["meddelande", "lista"])
+            .await
+            .contains("God jul, {{month_name}}!"));
+
+        admin(&["meddelande", "ta-bort", "1"]).await;
+        admin(&["meddelande", "ta-bort", "1"]).await;
+        let audit_log = storage.read(|data| data.audit_log.len()).await;
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(path.with_extension("lock")).unwrap();
+
+        assert!(storage.get_messages().await.is_empty());
+        assert_eq!(audit_log, 2);
+    }
+}
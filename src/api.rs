@@ -0,0 +1,194 @@
+use chrono::{Datelike, NaiveDate, Utc};
+use rocket::http::{ContentType, Status};
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::{get, routes, Route, State};
+use serde::Serialize;
+
+use std::sync::Arc;
+
+use crate::last_day::WorkingHours;
+use crate::shared_config::SharedConfiguration;
+
+// Keeps a single request from fanning out into too many calendar lookups
+const MAX_RANGE_DAYS: i64 = 366;
+
+const OPENAPI_DOCUMENT: &str = include_str!("openapi.json");
+
+type ApiResult<T> = Result<Json<T>, Custom<Json<ApiError>>>;
+
+#[derive(Serialize, Debug)]
+pub struct ApiError {
+    pub error: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct LastWorkdayResponse {
+    pub month: String,
+    pub last_workday: NaiveDate,
+}
+
+#[derive(Serialize, Debug)]
+pub struct WorkdaysResponse {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub count: usize,
+    pub workdays: Vec<NaiveDate>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct IsWorkdayResponse {
+    pub date: NaiveDate,
+    pub workday: bool,
+    pub hours: WorkingHours,
+}
+
+/// Routes for the public workday API, meant to be mounted under `/api/v1`.
+pub fn routes() -> Vec<Route> {
+    routes![last_workday, workdays, workday, openapi]
+}
+
+#[get("/last-workday?<month>")]
+async fn last_workday(
+    config: &State<Arc<SharedConfiguration>>,
+    month: Option<&str>,
+) -> ApiResult<LastWorkdayResponse> {
+    let first_day = match month {
+        Some(month) => parse_month(month)?,
+        None => Utc::now().date_naive().with_day(1).unwrap(),
+    };
+
+    let last_workday = config
+        .get()
+        .get_calendar()
+        .get_last_workday(&first_day)
+        .await
+        .map_err(upstream_error)?;
+
+    Ok(Json(LastWorkdayResponse {
+        month: first_day.format("%Y-%m").to_string(),
+        last_workday,
+    }))
+}
+
+#[get("/workdays?<from>&<to>")]
+async fn workdays(
+    config: &State<Arc<SharedConfiguration>>,
+    from: &str,
+    to: &str,
+) -> ApiResult<WorkdaysResponse> {
+    let from = parse_date("from", from)?;
+    let to = parse_date("to", to)?;
+
+    if to < from {
+        return Err(bad_request(String::from("'to' must not be before 'from'")));
+    }
+    if (to - from).num_days() >= MAX_RANGE_DAYS {
+        return Err(bad_request(format!(
+            "the range may span at most {} days",
+            MAX_RANGE_DAYS
+        )));
+    }
+
+    let workdays = config
+        .get()
+        .get_calendar()
+        .get_workdays(&from, &to)
+        .await
+        .map_err(upstream_error)?;
+
+    Ok(Json(WorkdaysResponse {
+        from,
+        to,
+        count: workdays.len(),
+        workdays,
+    }))
+}
+
+#[get("/is-workday?<date>")]
+async fn workday(
+    config: &State<Arc<SharedConfiguration>>,
+    date: &str,
+) -> ApiResult<IsWorkdayResponse> {
+    let date = parse_date("date", date)?;
+    let day = config
+        .get()
+        .get_calendar()
+        .get_day(&date)
+        .await
+        .map_err(upstream_error)?;
+
+    Ok(Json(IsWorkdayResponse {
+        date,
+        workday: day.is_workday(),
+        hours: day.hours,
+    }))
+}
+
+#[get("/openapi.json")]
+fn openapi() -> (ContentType, &'static str) {
+    (ContentType::JSON, OPENAPI_DOCUMENT)
+}
+
+fn parse_month(month: &str) -> Result<NaiveDate, Custom<Json<ApiError>>> {
+    NaiveDate::parse_from_str(format!("{}-01", month).as_str(), "%Y-%m-%d").map_err(|_| {
+        bad_request(format!(
+            "'month' must be formatted as YYYY-MM, got '{}'",
+            month
+        ))
+    })
+}
+
+fn parse_date(name: &str, date: &str) -> Result<NaiveDate, Custom<Json<ApiError>>> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| {
+        bad_request(format!(
+            "'{}' must be formatted as YYYY-MM-DD, got '{}'",
+            name, date
+        ))
+    })
+}
+
+fn bad_request(error: String) -> Custom<Json<ApiError>> {
+    Custom(Status::BadRequest, Json(ApiError { error }))
+}
+
+fn upstream_error(error: anyhow::Error) -> Custom<Json<ApiError>> {
+    println!("failed to look up calendar: {}", error);
+    Custom(
+        Status::ServiceUnavailable,
+        Json(ApiError {
+            error: String::from("the holiday calendar is currently unavailable"),
+        }),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+
+    use super::{parse_date, parse_month, OPENAPI_DOCUMENT};
+
+    #[test]
+    fn month_is_parsed_to_first_day() {
+        let month = parse_month("2020-10").expect("failed");
+
+        assert_eq!(month, NaiveDate::from_ymd_opt(2020, 10, 1).unwrap());
+    }
+
+    #[test]
+    fn invalid_dates_are_rejected() {
+        assert!(parse_month("2020-13").is_err());
+        assert!(parse_date("date", "2020-02-30").is_err());
+        assert!(parse_date("date", "yesterday").is_err());
+    }
+
+    #[test]
+    fn openapi_document_describes_all_routes() {
+        let document: serde_json::Value =
+            serde_json::from_str(OPENAPI_DOCUMENT).expect("invalid openapi document");
+
+        for path in ["/last-workday", "/workdays", "/is-workday"] {
+            assert!(document["paths"][path].is_object(), "missing {}", path);
+        }
+    }
+}
@@ -1,9 +1,15 @@
 use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use chrono_tz::{Europe::Stockholm, Tz};
 use reqwest::Client;
 use serde::Deserialize;
 
+// Users rarely change timezone, so there is no need to ask Slack on every command
+const USER_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
 #[derive(Deserialize, Debug)]
 pub struct Channel {
     pub id: String,
@@ -23,9 +29,25 @@ pub struct ChannelResponse {
     response_metadata: ResponseMetadata,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct User {
+    pub id: String,
+    pub name: String,
+    /// IANA timezone, e.g. "Europe/Oslo"
+    pub tz: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UserResponse {
+    ok: bool,
+    user: Option<User>,
+    error: Option<String>,
+}
+
 pub struct SlackClient {
     client: Client,
     token: String,
+    users: Mutex<HashMap<String, (Instant, User)>>,
 }
 
 #[async_trait::async_trait]
@@ -33,6 +55,26 @@ pub trait SlackClientTrait {
     async fn get_channel_id_by_name(&self, channel_name: &str) -> Option<String>;
     async fn get_channels(&self) -> Result<Vec<Channel>>;
     async fn post_message(&self, channel_id: &str, message: &str) -> Result<()>;
+    async fn get_user(&self, user_id: &str) -> Result<User>;
+}
+
+/// The timezone of a Slack user, or Stockholm if the user or timezone is unknown.
+pub async fn get_user_timezone(client: &impl SlackClientTrait, user_id: Option<&str>) -> Tz {
+    let user_id = match user_id {
+        Some(user_id) => user_id,
+        None => return Stockholm,
+    };
+
+    match client.get_user(user_id).await {
+        Ok(user) => user
+            .tz
+            .and_then(|tz| tz.parse::<Tz>().ok())
+            .unwrap_or(Stockholm),
+        Err(error) => {
+            println!("couldn't get user {}: {}", user_id, error);
+            Stockholm
+        }
+    }
 }
 
 impl SlackClient {
@@ -40,7 +82,8 @@ impl SlackClient {
         let token = std::env::var("JOEL_BOT_SLACK_TOKEN")?;
         Ok(SlackClient {
             client: Client::new(),
-            token: String::from(token),
+            token,
+            users: Mutex::new(HashMap::new()),
         })
     }
 }
@@ -102,4 +145,39 @@ impl SlackClientTrait for SlackClient {
 
         Ok(())
     }
+
+    async fn get_user(&self, user_id: &str) -> Result<User> {
+        if let Some((fetched, user)) = self.users.lock().unwrap().get(user_id) {
+            if fetched.elapsed() < USER_CACHE_TTL {
+                return Ok(user.clone());
+            }
+        }
+
+        let mut params = HashMap::new();
+        params.insert("token", self.token.clone());
+        params.insert("user", user_id.to_string());
+
+        let response: UserResponse = self
+            .client
+            .post("https://slack.com/api/users.info")
+            .form(&params)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        match (response.ok, response.user) {
+            (true, Some(user)) => {
+                self.users
+                    .lock()
+                    .unwrap()
+                    .insert(user_id.to_string(), (Instant::now(), user.clone()));
+                Ok(user)
+            }
+            _ => Err(anyhow!(
+                "users.info failed: {}",
+                response.error.unwrap_or_default()
+            )),
+        }
+    }
 }
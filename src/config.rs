@@ -1,16 +1,298 @@
 use std::collections::HashMap;
 use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
 
+use chrono::NaiveDate;
 use rand::prelude::*;
 use serde::Deserialize;
 use serde_yaml;
 
-type Part = HashMap<String, Vec<String>>;
+use crate::config_source::ConfigSource;
+use crate::holidays::Country;
+use crate::last_day::{Calendar, WorkingHours};
+use crate::locale::Locale;
+use crate::office_hours::OfficeHours;
+use crate::replies::{Replies, Reply, ReplyPools, REPLIES};
+use crate::reporting::ReportingPeriod;
+use crate::storage::{Storage, StorageData, StoredMessage};
+use crate::template::{render, Variables, INTRO_VARIABLES, REMINDER_VARIABLES};
+
+type Part = HashMap<String, Vec<Message>>;
+
+pub const CONFIG_PATH: &str = "config.yaml";
+
+/// The parts a time report reminder is put together from.
+pub const TIME_REPORT_PARTS: [&str; 3] = ["beginning", "middle", "end"];
 
 #[derive(Deserialize, Debug)]
 pub struct Configuration {
     intro: Intro,
     time_report: TimeReport,
+    /// Reminders for clients that don't report monthly, see [`ClientTimeReports`]
+    #[serde(default)]
+    client_time_report: ClientTimeReports,
+    #[serde(default)]
+    working_time: WorkingTime,
+    #[serde(default)]
+    countries: Countries,
+    /// Clients with their own reporting periods, keyed by the name used in `tid <klient>`
+    #[serde(default)]
+    clients: HashMap<String, ReportingClient>,
+    #[serde(default)]
+    payday: Payday,
+    #[serde(default)]
+    calculating: Calculating,
+    #[serde(default)]
+    locales: Locales,
+    /// What joel-bot answers with, see [`Reply`]
+    #[serde(default)]
+    replies: ReplyPools,
+    /// Slack user ids of those who may use the `admin` commands
+    #[serde(default)]
+    admins: Vec<String>,
+}
+
+/// Something in the configuration that would make the bot fail, at a dotted path like
+/// `time_report.beginning`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigProblem {
+    pub path: Vec<String>,
+    pub message: String,
+}
+
+impl ConfigProblem {
+    pub fn new(path: &[&str], message: &str) -> Self {
+        ConfigProblem {
+            path: path.iter().map(|key| key.to_string()).collect(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.path.join("."), self.message)
+    }
+}
+
+/// A list of messages, e.g. `time_report.beginning.12`.
+pub struct MessagePool<'a> {
+    pub path: Vec<&'a str>,
+    pub messages: Vec<&'a str>,
+    /// The context the messages are picked for, for reminder messages
+    pub context: Option<&'a str>,
+    /// The variables the messages are rendered with
+    pub variables: &'static [&'static str],
+}
+
+impl<'a> MessagePool<'a> {
+    fn new(path: Vec<&'a str>, messages: &'a [String], variables: &'static [&'static str]) -> Self {
+        MessagePool {
+            path,
+            messages: messages.iter().map(String::as_str).collect(),
+            context: None,
+            variables,
+        }
+    }
+}
+
+/// A message to pick from, either just the text or with a weight, e.g.
+/// `{ text: "Hej!", weight: 3 }` to be picked three times as often as the others.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Message {
+    Text(String),
+    Weighted {
+        text: String,
+        #[serde(default = "default_weight")]
+        weight: u32,
+    },
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+impl Message {
+    pub fn get_text(&self) -> &str {
+        match self {
+            Message::Text(text) | Message::Weighted { text, .. } => text,
+        }
+    }
+
+    pub fn get_weight(&self) -> u32 {
+        match self {
+            Message::Text(_) => default_weight(),
+            Message::Weighted { weight, .. } => *weight,
+        }
+    }
+}
+
+/// Picks a message by weight, avoiding the `recent` ones unless there is nothing else to pick.
+pub fn choose_message<'a>(messages: &'a [Message], recent: &[String]) -> Option<&'a Message> {
+    choose_message_with(messages, recent, &mut rand::rng())
+}
+
+/// [`choose_message`] with the given random number generator, e.g. a seeded one for previews.
+pub fn choose_message_with<'a>(
+    messages: &'a [Message],
+    recent: &[String],
+    rng: &mut impl Rng,
+) -> Option<&'a Message> {
+    let candidates: Vec<&Message> = messages
+        .iter()
+        .filter(|message| !recent.iter().any(|text| text == message.get_text()))
+        .collect();
+
+    candidates
+        .choose_weighted(rng, |message| message.get_weight())
+        .ok()
+        .copied()
+        .or_else(|| {
+            messages
+                .choose_weighted(rng, |message| message.get_weight())
+                .ok()
+        })
+}
+
+/// The "calculating" steps shown by `/joel` before it answers.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Calculating {
+    #[serde(default = "default_calculating_steps")]
+    pub steps: usize,
+    #[serde(default = "default_calculating_delay")]
+    pub delay_ms: u64,
+    #[serde(default)]
+    pub phrases: Vec<String>,
+}
+
+fn default_calculating_steps() -> usize {
+    2
+}
+
+fn default_calculating_delay() -> u64 {
+    2000
+}
+
+impl Default for Calculating {
+    fn default() -> Self {
+        Calculating {
+            steps: default_calculating_steps(),
+            delay_ms: default_calculating_delay(),
+            phrases: Vec::new(),
+        }
+    }
+}
+
+impl Calculating {
+    pub fn get_delay(&self) -> Duration {
+        Duration::from_millis(self.delay_ms)
+    }
+
+    /// `steps` random phrases, without repeating any.
+    pub fn get_phrases(&self) -> Vec<String> {
+        self.phrases
+            .choose_multiple(&mut rand::rng(), self.steps)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Pay day, moved to the previous workday when it falls on a weekend or holiday.
+#[derive(Deserialize, Debug)]
+pub struct Payday {
+    #[serde(default = "default_payday")]
+    pub day: u32,
+    /// Name of the channel to announce pay day in, if any
+    pub channel: Option<String>,
+    #[serde(default)]
+    messages: Vec<String>,
+}
+
+fn default_payday() -> u32 {
+    25
+}
+
+impl Default for Payday {
+    fn default() -> Self {
+        Payday {
+            day: default_payday(),
+            channel: None,
+            messages: Vec::new(),
+        }
+    }
+}
+
+impl Payday {
+    pub fn get_period(&self) -> ReportingPeriod {
+        ReportingPeriod::Cutoff { day: self.day }
+    }
+
+    pub fn get_message(&self) -> Option<String> {
+        self.get_message_with(&mut rand::rng())
+    }
+
+    pub fn get_message_with(&self, rng: &mut impl Rng) -> Option<String> {
+        self.messages.choose(rng).cloned()
+    }
+
+    pub fn get_messages(&self) -> &[String] {
+        &self.messages
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ReportingClient {
+    #[serde(default)]
+    pub period: ReportingPeriod,
+    /// Name of the channel that gets reminded on the deadline
+    pub channel: Option<String>,
+}
+
+/// Which country's holidays apply, for consultants working on client sites abroad.
+#[derive(Deserialize, Debug, Default)]
+pub struct Countries {
+    #[serde(default)]
+    default: Country,
+    /// Slack user id to country
+    #[serde(default)]
+    users: HashMap<String, Country>,
+    /// Slack channel id to country
+    #[serde(default)]
+    channels: HashMap<String, Country>,
+}
+
+/// Which language to answer in, for consultants who don't speak Swedish.
+#[derive(Deserialize, Debug, Default)]
+pub struct Locales {
+    #[serde(default)]
+    default: Locale,
+    /// Slack user id to locale
+    #[serde(default)]
+    users: HashMap<String, Locale>,
+    /// Slack channel id to locale
+    #[serde(default)]
+    channels: HashMap<String, Locale>,
+    /// Messages in other languages, where anything left out is taken from the Swedish ones
+    #[serde(default)]
+    messages: HashMap<Locale, LocalizedMessages>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct LocalizedMessages {
+    intro: Option<Intro>,
+    time_report: Option<TimeReport>,
+    client_time_report: Option<ClientTimeReports>,
+    calculating: Option<LocalizedCalculating>,
+    replies: Option<ReplyPools>,
+}
+
+/// The steps and delay are the same in every language, only the phrases differ.
+#[derive(Deserialize, Debug)]
+pub struct LocalizedCalculating {
+    phrases: Vec<String>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -18,6 +300,126 @@ pub struct TimeReport {
     beginning: Part,
     middle: Part,
     end: Part,
+    /// How many of the latest messages per part to avoid repeating
+    #[serde(default = "default_history")]
+    history: usize,
+}
+
+fn default_history() -> usize {
+    3
+}
+
+impl TimeReport {
+    fn get_parts(&self) -> [(&'static str, &Part); 3] {
+        let [beginning, middle, end] = TIME_REPORT_PARTS;
+        [
+            (beginning, &self.beginning),
+            (middle, &self.middle),
+            (end, &self.end),
+        ]
+    }
+
+    /// Checks the parts, where `path` is where the time report is, e.g. `["time_report"]`.
+    fn validate(&self, path: &[&str]) -> Vec<ConfigProblem> {
+        let mut problems = Vec::new();
+
+        for (name, part) in self.get_parts() {
+            if !part.contains_key("general") {
+                problems.push(ConfigProblem::new(
+                    &[path, &[name]].concat(),
+                    "has no general messages",
+                ));
+            }
+            for (context, messages) in part {
+                if messages.iter().all(|message| message.get_weight() == 0) {
+                    problems.push(ConfigProblem::new(
+                        &[path, &[name, context]].concat(),
+                        "has no messages with a weight above 0",
+                    ));
+                }
+            }
+        }
+        problems
+    }
+
+    fn get_pools<'a>(&'a self, path: &[&'a str]) -> Vec<MessagePool<'a>> {
+        let mut pools = Vec::new();
+
+        for (name, part) in self.get_parts() {
+            let mut contexts: Vec<&String> = part.keys().collect();
+            contexts.sort();
+            for context in contexts {
+                pools.push(MessagePool {
+                    path: [path, &[name, context]].concat(),
+                    messages: part[context].iter().map(Message::get_text).collect(),
+                    context: Some(context),
+                    variables: &REMINDER_VARIABLES,
+                });
+            }
+        }
+        pools
+    }
+}
+
+/// Reminders for clients with weekly, biweekly or cutoff periods, which can't say things like
+/// "sista arbetsdagen i månaden". Monthly clients get the `time_report` reminders.
+#[derive(Deserialize, Debug, Default)]
+pub struct ClientTimeReports {
+    weekly: Option<TimeReport>,
+    biweekly: Option<TimeReport>,
+    cutoff: Option<TimeReport>,
+}
+
+impl ClientTimeReports {
+    fn get(&self, period: &ReportingPeriod) -> Option<&TimeReport> {
+        match period {
+            ReportingPeriod::Monthly => None,
+            ReportingPeriod::Weekly { .. } => self.weekly.as_ref(),
+            ReportingPeriod::Biweekly { .. } => self.biweekly.as_ref(),
+            ReportingPeriod::Cutoff { .. } => self.cutoff.as_ref(),
+        }
+    }
+
+    fn get_reports(&self) -> Vec<(&'static str, &TimeReport)> {
+        [
+            ("weekly", &self.weekly),
+            ("biweekly", &self.biweekly),
+            ("cutoff", &self.cutoff),
+        ]
+        .into_iter()
+        .filter_map(|(kind, report)| report.as_ref().map(|report| (kind, report)))
+        .collect()
+    }
+}
+
+/// `part` with the messages added from Slack for it, see [`StoredMessage`].
+fn with_stored_messages(part: &Part, name: &str, stored: &[StoredMessage]) -> Part {
+    let mut part = part.clone();
+    for message in stored.iter().filter(|message| message.part == name) {
+        part.entry(message.context.clone())
+            .or_default()
+            .push(Message::Text(message.text.clone()));
+    }
+    part
+}
+
+fn render_reminder(beginning: &str, middle: &str, end: &str, variables: &Variables) -> String {
+    render(
+        &format!("<!channel> {}\n{}\n{}", beginning, middle, end),
+        variables,
+    )
+}
+
+// Swedish keeps the keys it had before there were other locales, so its history is kept
+fn get_history_key(locale: Locale, period: &ReportingPeriod, part: &str) -> String {
+    let key = match period {
+        ReportingPeriod::Monthly => format!("time_report.{}", part),
+        period => format!("client_time_report.{}.{}", period.get_kind(), part),
+    };
+    match locale {
+        Locale::Sv => key,
+        locale => format!("{}.{}", locale.get_code(), key),
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -34,62 +436,824 @@ pub struct Credits {
     names: Vec<String>,
 }
 
+impl Intro {
+    fn get_pools<'a>(&'a self, prefix: &[&'a str]) -> Vec<MessagePool<'a>> {
+        let path = |keys: &[&'a str]| [prefix, keys].concat();
+        vec![
+            MessagePool::new(
+                path(&["intro", "greetings"]),
+                &self.greetings,
+                &INTRO_VARIABLES,
+            ),
+            MessagePool::new(
+                path(&["intro", "about_me"]),
+                std::slice::from_ref(&self.about_me),
+                &INTRO_VARIABLES,
+            ),
+            MessagePool::new(
+                path(&["intro", "features"]),
+                &self.features,
+                &INTRO_VARIABLES,
+            ),
+            MessagePool::new(
+                path(&["intro", "credits", "names"]),
+                &self.credits.names,
+                &[],
+            ),
+        ]
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct WorkingTime {
+    #[serde(default = "default_hours_per_day")]
+    pub hours_per_day: f64,
+    /// Default office hours for `/gg`, users may set their own with `gg sätt`
+    #[serde(default)]
+    pub office_hours: OfficeHours,
+    /// Recurring days with other working hours than their public holiday status suggests
+    #[serde(default)]
+    pub days: Vec<DayRule>,
+    /// Company specific days, e.g. klämdagar or closing between Christmas and New Year
+    #[serde(default)]
+    pub overrides: Vec<CalendarOverride>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct DayRule {
+    /// Matches the workday before the named holiday, e.g. "Trettondedag jul"
+    pub before: Option<String>,
+    /// Matches a recurring date formatted as MM-DD, e.g. "12-23"
+    pub date: Option<String>,
+    pub hours: WorkingHours,
+}
+
+/// A single date or a date range, both inclusive, that takes precedence over the holiday provider.
+#[derive(Deserialize, Debug, Clone)]
+pub struct CalendarOverride {
+    pub date: Option<NaiveDate>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub hours: WorkingHours,
+    pub reason: Option<String>,
+}
+
+impl CalendarOverride {
+    pub fn contains(&self, date: &NaiveDate) -> bool {
+        match (&self.date, &self.from, &self.to) {
+            (Some(day), _, _) => day == date,
+            (None, Some(from), Some(to)) => from <= date && date <= to,
+            _ => false,
+        }
+    }
+
+    /// What keeps the override from matching what it is meant to, if anything.
+    fn get_problem(&self) -> Option<&'static str> {
+        match (&self.date, &self.from, &self.to) {
+            (Some(_), None, None) => None,
+            (Some(_), _, _) => Some("has both date and from or to"),
+            (None, Some(from), Some(to)) if from > to => Some("has from after to"),
+            (None, Some(_), Some(_)) => None,
+            (None, None, None) => Some("needs either date or both from and to"),
+            (None, _, _) => Some("needs both from and to"),
+        }
+    }
+}
+
+fn default_hours_per_day() -> f64 {
+    8.0
+}
+
+impl Default for WorkingTime {
+    fn default() -> Self {
+        WorkingTime {
+            hours_per_day: default_hours_per_day(),
+            office_hours: OfficeHours::default(),
+            days: Vec::new(),
+            overrides: Vec::new(),
+        }
+    }
+}
+
 impl Configuration {
-    pub fn get_authors(&self) -> String {
-        let names = self
-            .intro
-            .credits
+    pub fn get_authors(&self, locale: Locale) -> String {
+        let credits = &self.get_intro(locale).credits;
+        let names = credits
             .names
             .iter()
             .map(|name| format!("\t- {}", name))
             .collect::<Vec<String>>()
             .join("\n");
-        format!("{}\n\n{}", self.intro.credits.intro, names)
+        format!("{}\n\n{}", credits.intro, names)
+    }
+
+    pub fn get_working_time(&self) -> &WorkingTime {
+        &self.working_time
+    }
+
+    pub fn get_client(&self, name: &str) -> Option<&ReportingClient> {
+        self.clients
+            .iter()
+            .find(|(client, _)| client.eq_ignore_ascii_case(name))
+            .map(|(_, client)| client)
+    }
+
+    pub fn get_clients(&self) -> &HashMap<String, ReportingClient> {
+        &self.clients
+    }
+
+    pub fn get_calculating(&self, locale: Locale) -> Calculating {
+        let mut calculating = self.calculating.clone();
+        if let Some(localized) = self
+            .get_localized(locale)
+            .and_then(|messages| messages.calculating.as_ref())
+        {
+            calculating.phrases = localized.phrases.clone();
+        }
+        calculating
+    }
+
+    pub fn get_payday(&self) -> &Payday {
+        &self.payday
+    }
+
+    pub fn get_calendar(&self) -> Calendar {
+        Calendar::new(self.working_time.clone(), self.countries.default)
+    }
+
+    /// The calendar for a Slack user, where a user setting takes precedence over the channel.
+    pub fn get_calendar_for(&self, user: Option<&str>, channel: Option<&str>) -> Calendar {
+        let country = user
+            .and_then(|user| self.countries.users.get(user))
+            .or_else(|| channel.and_then(|channel| self.countries.channels.get(channel)))
+            .unwrap_or(&self.countries.default);
+
+        Calendar::new(self.working_time.clone(), *country)
+    }
+
+    /// The locale for a Slack user, where a user setting takes precedence over the channel.
+    pub fn get_locale_for(&self, user: Option<&str>, channel: Option<&str>) -> Locale {
+        *user
+            .and_then(|user| self.locales.users.get(user))
+            .or_else(|| channel.and_then(|channel| self.locales.channels.get(channel)))
+            .unwrap_or(&self.locales.default)
+    }
+
+    fn get_localized(&self, locale: Locale) -> Option<&LocalizedMessages> {
+        self.locales.messages.get(&locale)
     }
 
-    pub fn get_message(&self, context: &str) -> String {
-        let beginning = Configuration::get_message_part(&self.time_report.beginning, context);
-        let middle = Configuration::get_message_part(&self.time_report.middle, context);
-        let end = Configuration::get_message_part(&self.time_report.end, context);
-        format!("<!channel> {}\n{}\n{}", beginning, middle, end)
+    pub fn get_replies(&self, locale: Locale) -> Replies<'_> {
+        let localized = self
+            .get_localized(locale)
+            .and_then(|messages| messages.replies.as_ref());
+        Replies::new(locale, localized, &self.replies)
     }
 
-    pub fn get_introduction(&self) -> String {
-        let index = rand::rng().random_range(0..self.intro.greetings.len());
-        let greeting = &self.intro.greetings[index];
-        let features = self
-            .intro
+    fn get_intro(&self, locale: Locale) -> &Intro {
+        self.get_localized(locale)
+            .and_then(|messages| messages.intro.as_ref())
+            .unwrap_or(&self.intro)
+    }
+
+    pub fn is_admin(&self, user: &str) -> bool {
+        self.admins.iter().any(|admin| admin == user)
+    }
+
+    fn has_localized_time_report(&self, locale: Locale) -> bool {
+        self.get_localized(locale)
+            .is_some_and(|messages| messages.time_report.is_some())
+    }
+
+    fn get_time_report(&self, locale: Locale) -> &TimeReport {
+        self.get_localized(locale)
+            .and_then(|messages| messages.time_report.as_ref())
+            .unwrap_or(&self.time_report)
+    }
+
+    // Validation makes sure there are reminders for every period a client uses
+    fn get_time_report_for(&self, locale: Locale, period: &ReportingPeriod) -> &TimeReport {
+        self.get_localized(locale)
+            .and_then(|messages| messages.client_time_report.as_ref())
+            .and_then(|reports| reports.get(period))
+            .or_else(|| self.client_time_report.get(period))
+            .unwrap_or_else(|| self.get_time_report(locale))
+    }
+
+    /// Puts together a time report reminder for `period`, remembering what was picked in
+    /// `storage` so the next reminders are different.
+    /// Each part is picked from the first of `contexts` it has messages for, see
+    /// [`get_contexts`](crate::message_context::get_contexts).
+    pub async fn get_message(
+        &self,
+        locale: Locale,
+        period: &ReportingPeriod,
+        contexts: &[String],
+        variables: &Variables,
+        storage: &Storage,
+    ) -> String {
+        let data = storage.read(StorageData::clone).await;
+        let picked = self.pick_message_parts(locale, period, contexts, &data, &mut rand::rng());
+
+        let history = self.get_time_report_for(locale, period).history;
+        let remembered = storage
+            .update(|data| {
+                for (key, message) in &picked {
+                    data.remember_message(key, message, history);
+                }
+            })
+            .await;
+        if let Err(error) = remembered {
+            println!("couldn't remember the picked messages: {}", error);
+        }
+
+        let [beginning, middle, end] = [&picked[0].1, &picked[1].1, &picked[2].1];
+        render_reminder(beginning, middle, end, variables)
+    }
+
+    /// The reminder [`get_message`](Configuration::get_message) would put together when picking
+    /// with `rng`, without remembering anything in `storage`.
+    pub async fn preview_message(
+        &self,
+        locale: Locale,
+        period: &ReportingPeriod,
+        contexts: &[String],
+        variables: &Variables,
+        storage: &Storage,
+        rng: &mut impl Rng,
+    ) -> String {
+        let data = storage.read(StorageData::clone).await;
+        let picked = self.pick_message_parts(locale, period, contexts, &data, rng);
+        let [beginning, middle, end] = [&picked[0].1, &picked[1].1, &picked[2].1];
+        render_reminder(beginning, middle, end, variables)
+    }
+
+    /// Every reminder that can be put together for `contexts`, whatever was picked before.
+    pub async fn preview_all_messages(
+        &self,
+        locale: Locale,
+        period: &ReportingPeriod,
+        contexts: &[String],
+        variables: &Variables,
+        storage: &Storage,
+    ) -> Vec<String> {
+        let data = storage.read(StorageData::clone).await;
+        let parts: Vec<Vec<String>> = self
+            .get_message_pools(locale, period, contexts, &data)
+            .into_iter()
+            .map(|(_, messages)| {
+                messages
+                    .iter()
+                    .filter(|message| message.get_weight() > 0)
+                    .map(|message| message.get_text().to_string())
+                    .collect()
+            })
+            .collect();
+
+        let mut reminders = Vec::new();
+        for beginning in &parts[0] {
+            for middle in &parts[1] {
+                for end in &parts[2] {
+                    reminders.push(render_reminder(beginning, middle, end, variables));
+                }
+            }
+        }
+        reminders
+    }
+
+    fn pick_message_parts(
+        &self,
+        locale: Locale,
+        period: &ReportingPeriod,
+        contexts: &[String],
+        data: &StorageData,
+        rng: &mut impl Rng,
+    ) -> Vec<(String, String)> {
+        self.get_message_pools(locale, period, contexts, data)
+            .into_iter()
+            .map(|(key, messages)| {
+                let recent = data
+                    .recent_messages
+                    .get(&key)
+                    .map_or(&[][..], Vec::as_slice);
+                let message = choose_message_with(&messages, recent, rng)
+                    .map(|message| message.get_text().to_string())
+                    .unwrap_or_default();
+                (key, message)
+            })
+            .collect()
+    }
+
+    // The messages each part is picked from, with the key its history is remembered by
+    fn get_message_pools(
+        &self,
+        locale: Locale,
+        period: &ReportingPeriod,
+        contexts: &[String],
+        data: &StorageData,
+    ) -> Vec<(String, Vec<Message>)> {
+        // Messages added from Slack are Swedish monthly ones, so they aren't used with
+        // translated or client messages
+        let stored: &[StoredMessage] =
+            if *period != ReportingPeriod::Monthly || self.has_localized_time_report(locale) {
+                &[]
+            } else {
+                &data.messages
+            };
+
+        self.get_time_report_for(locale, period)
+            .get_parts()
+            .iter()
+            .map(|(name, part)| {
+                let part = with_stored_messages(part, name, stored);
+                let messages = match contexts.iter().find_map(|context| part.get(context)) {
+                    None => part.get("general").unwrap(),
+                    Some(messages) => messages,
+                };
+                (get_history_key(locale, period, name), messages.clone())
+            })
+            .collect()
+    }
+
+    pub fn get_introduction(&self, locale: Locale, variables: &Variables) -> String {
+        let intro = self.get_intro(locale);
+        let index = rand::rng().random_range(0..intro.greetings.len());
+        let greeting = &intro.greetings[index];
+        let features = intro
             .features
             .iter()
             .map(|feature| format!("\t- {}", feature))
             .collect::<Vec<String>>()
             .join("\n");
+        let heading = self
+            .get_replies(locale)
+            .get(Reply::IntroCommands, variables);
 
         // Sexiest line of code everest!
-        format!(
-            "{}\n\n{}\n\nSaker ni kan fråga (med `@joel-bot <kommando>`:\n{}",
-            greeting, self.intro.about_me, features
+        render(
+            &format!(
+                "{}\n\n{}\n\n{}\n{}",
+                greeting, intro.about_me, heading, features
+            ),
+            variables,
         )
     }
 
+    /// Reads the configuration from `JOEL_BOT_CONFIG` or `config.yaml`, see [`ConfigSource`].
     pub fn read() -> Result<Configuration, Box<dyn Error>> {
-        let file = std::fs::File::open("config.yaml")?;
-        let config: Configuration = serde_yaml::from_reader(file)?;
+        ConfigSource::from_args(&[]).load()
+    }
+
+    /// Reads and validates the configuration at `path`, without any layers or overrides.
+    pub fn load(path: &Path) -> Result<Configuration, Box<dyn Error>> {
+        let file = std::fs::File::open(path)?;
+        Configuration::from_value(serde_yaml::from_reader(file)?)
+    }
+
+    pub fn from_value(value: serde_yaml::Value) -> Result<Configuration, Box<dyn Error>> {
+        let config = Configuration::parse_value(value)?;
+
+        let problems = config.validate();
+        if !problems.is_empty() {
+            let problems: Vec<String> =
+                problems.iter().map(|problem| problem.to_string()).collect();
+            return Err(format!("invalid configuration: {}", problems.join(", ")).into());
+        }
         Ok(config)
     }
 
-    fn get_message_part(part: &Part, context: &str) -> String {
-        let mut random = rand::rng();
+    /// Reads the configuration without validating it.
+    pub fn parse_value(value: serde_yaml::Value) -> Result<Configuration, serde_yaml::Error> {
+        // Going through text lets month keys like `12:` be read as strings, like they are from a file
+        serde_yaml::from_str(&serde_yaml::to_string(&value)?)
+    }
+
+    /// Everything that would make the bot fail when it is used, empty if the configuration is fine.
+    pub fn validate(&self) -> Vec<ConfigProblem> {
+        let mut problems = Vec::new();
+
+        for (prefix, intro, time_report) in self.get_localizations() {
+            if let Some(intro) = intro {
+                if intro.greetings.is_empty() {
+                    problems.push(ConfigProblem::new(
+                        &[prefix.as_slice(), &["intro", "greetings"]].concat(),
+                        "is empty",
+                    ));
+                }
+            }
+            if let Some(time_report) = time_report {
+                problems
+                    .extend(time_report.validate(&[prefix.as_slice(), &["time_report"]].concat()));
+            }
+        }
+        for (path, time_report) in self.get_client_time_reports() {
+            problems.extend(time_report.validate(&path));
+        }
+        for (name, client) in &self.clients {
+            if client.period != ReportingPeriod::Monthly
+                && self.client_time_report.get(&client.period).is_none()
+            {
+                let kind = client.period.get_kind();
+                problems.push(ConfigProblem::new(
+                    &["clients", name, "period"],
+                    &format!("is {} but client_time_report.{} is missing", kind, kind),
+                ));
+            }
+            if let ReportingPeriod::Cutoff { day } = client.period {
+                if !(1..=31).contains(&day) {
+                    problems.push(ConfigProblem::new(
+                        &["clients", name, "period", "day"],
+                        "must be a day of the month, 1-31",
+                    ));
+                }
+            }
+        }
+
+        for reply in REPLIES {
+            if !self.replies.contains_key(reply) {
+                problems.push(ConfigProblem::new(
+                    &["replies", reply.get_key()],
+                    "is missing",
+                ));
+            }
+        }
+        for (prefix, replies) in self.get_localized_replies() {
+            for (reply, messages) in replies {
+                if messages.iter().all(|message| message.get_weight() == 0) {
+                    problems.push(ConfigProblem::new(
+                        &[prefix.as_slice(), &["replies", reply.get_key()]].concat(),
+                        "has no messages with a weight above 0",
+                    ));
+                }
+            }
+        }
+
+        if self.payday.channel.is_some() && self.payday.messages.is_empty() {
+            problems.push(ConfigProblem::new(
+                &["payday", "messages"],
+                "is empty but payday.channel is set",
+            ));
+        }
+        if !(1..=31).contains(&self.payday.day) {
+            problems.push(ConfigProblem::new(
+                &["payday", "day"],
+                "must be a day of the month, 1-31",
+            ));
+        }
+
+        let office_hours = &self.working_time.office_hours;
+        if office_hours.start >= office_hours.end {
+            problems.push(ConfigProblem::new(
+                &["working_time", "office_hours"],
+                "must start before they end",
+            ));
+        }
+        for (index, company_day) in self.working_time.overrides.iter().enumerate() {
+            if let Some(problem) = company_day.get_problem() {
+                problems.push(ConfigProblem::new(
+                    &["working_time", "overrides", &index.to_string()],
+                    problem,
+                ));
+            }
+        }
+
+        problems.sort_by(|a, b| a.path.cmp(&b.path));
+        problems
+    }
+
+    /// All lists of messages that are picked from, with where they are in the configuration.
+    pub fn get_pools(&self) -> Vec<MessagePool<'_>> {
+        let mut pools = Vec::new();
+
+        for (prefix, intro, time_report) in self.get_localizations() {
+            if let Some(intro) = intro {
+                pools.extend(intro.get_pools(&prefix));
+            }
+            if let Some(time_report) = time_report {
+                pools
+                    .extend(time_report.get_pools(&[prefix.as_slice(), &["time_report"]].concat()));
+            }
+        }
+        for (path, time_report) in self.get_client_time_reports() {
+            pools.extend(time_report.get_pools(&path));
+        }
+
+        pools.push(MessagePool::new(
+            vec!["payday", "messages"],
+            &self.payday.messages,
+            &[],
+        ));
+        pools.push(MessagePool::new(
+            vec!["calculating", "phrases"],
+            &self.calculating.phrases,
+            &[],
+        ));
+
+        for (prefix, replies) in self.get_localized_replies() {
+            let mut replies: Vec<(&Reply, &Vec<Message>)> = replies.iter().collect();
+            replies.sort_by_key(|(reply, _)| reply.get_key());
+            for (reply, messages) in replies {
+                pools.push(MessagePool {
+                    path: [prefix.as_slice(), &["replies", reply.get_key()]].concat(),
+                    messages: messages.iter().map(Message::get_text).collect(),
+                    context: None,
+                    variables: reply.get_variables(),
+                });
+            }
+        }
+
+        for (locale, messages) in self.get_sorted_locales() {
+            if let Some(calculating) = &messages.calculating {
+                pools.push(MessagePool::new(
+                    vec![
+                        "locales",
+                        "messages",
+                        locale.get_code(),
+                        "calculating",
+                        "phrases",
+                    ],
+                    &calculating.phrases,
+                    &[],
+                ));
+            }
+        }
+        pools
+    }
+
+    /// The Swedish messages first and then those of other locales, with the path to where they are.
+    fn get_localizations(&self) -> Vec<(Vec<&'static str>, Option<&Intro>, Option<&TimeReport>)> {
+        let mut localizations = vec![(Vec::new(), Some(&self.intro), Some(&self.time_report))];
+
+        for (locale, messages) in self.get_sorted_locales() {
+            localizations.push((
+                vec!["locales", "messages", locale.get_code()],
+                messages.intro.as_ref(),
+                messages.time_report.as_ref(),
+            ));
+        }
+        localizations
+    }
+
+    /// The client reminders in every locale, with the path to where they are.
+    fn get_client_time_reports(&self) -> Vec<(Vec<&'static str>, &TimeReport)> {
+        let mut localized = vec![(Vec::new(), &self.client_time_report)];
+        for (locale, messages) in self.get_sorted_locales() {
+            if let Some(reports) = &messages.client_time_report {
+                localized.push((vec!["locales", "messages", locale.get_code()], reports));
+            }
+        }
 
-        let part = match part.get(context) {
-            None => part.get("general").unwrap(),
-            Some(part) => part,
+        let mut reports = Vec::new();
+        for (prefix, client_time_report) in localized {
+            for (kind, time_report) in client_time_report.get_reports() {
+                reports.push((
+                    [prefix.as_slice(), &["client_time_report", kind]].concat(),
+                    time_report,
+                ));
+            }
+        }
+        reports
+    }
+
+    fn get_localized_replies(&self) -> Vec<(Vec<&'static str>, &ReplyPools)> {
+        let mut replies = vec![(Vec::new(), &self.replies)];
+
+        for (locale, messages) in self.get_sorted_locales() {
+            if let Some(localized) = &messages.replies {
+                replies.push((vec!["locales", "messages", locale.get_code()], localized));
+            }
+        }
+        replies
+    }
+
+    fn get_sorted_locales(&self) -> Vec<(&Locale, &LocalizedMessages)> {
+        let mut locales: Vec<(&Locale, &LocalizedMessages)> =
+            self.locales.messages.iter().collect();
+        locales.sort_by_key(|(locale, _)| locale.get_code());
+        locales
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Weekday;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::{choose_message, Configuration, Message};
+    use crate::locale::Locale;
+    use crate::replies::REPLIES;
+    use crate::reporting::ReportingPeriod;
+    use crate::storage::Storage;
+    use crate::template::Variables;
+
+    const CONFIG: &str = "intro:
+  greetings: [Hej]
+  about_me: Jag är joel-bot
+  features: []
+  credits: { intro: Skapad av, names: [] }
+time_report:
+  beginning: { general: [Hej] }
+  middle: { general: [Tidrapportera] }
+  end: { general: [Hejdå] }
+locales:
+  users: { U1: en, U2: sv }
+  channels: { C1: en }
+  messages:
+    en:
+      intro:
+        greetings: [Hi]
+        about_me: I am joel-bot
+        features: []
+        credits: { intro: Made by, names: [] }
+";
+
+    #[test]
+    fn messages_may_have_weights() {
+        let messages: Vec<Message> =
+            serde_yaml::from_str("- Hej\n- text: Hopp\n  weight: 3\n- text: Aldrig\n  weight: 0")
+                .expect("failed to parse");
+
+        assert_eq!(messages[0].get_weight(), 1);
+        assert_eq!(messages[1].get_text(), "Hopp");
+        assert_eq!(messages[1].get_weight(), 3);
+
+        for _ in 0..20 {
+            assert_ne!(choose_message(&messages, &[]).unwrap().get_text(), "Aldrig");
+        }
+    }
+
+    #[test]
+    fn recent_messages_are_avoided() {
+        let messages = vec![
+            Message::Text(String::from("Hej")),
+            Message::Text(String::from("Hopp")),
+        ];
+
+        for _ in 0..20 {
+            let message = choose_message(&messages, &[String::from("Hej")]).unwrap();
+            assert_eq!(message.get_text(), "Hopp");
+        }
+
+        // Everything being recent is better than saying nothing
+        let recent = [String::from("Hej"), String::from("Hopp")];
+        assert!(choose_message(&messages, &recent).is_some());
+    }
+
+    #[test]
+    fn locales_are_picked_per_user_and_channel() {
+        let config: Configuration = serde_yaml::from_str(CONFIG).expect("failed to parse");
+
+        assert_eq!(config.get_locale_for(Some("U1"), None), Locale::En);
+        assert_eq!(config.get_locale_for(Some("U2"), Some("C1")), Locale::Sv);
+        assert_eq!(config.get_locale_for(Some("U3"), Some("C1")), Locale::En);
+        assert_eq!(config.get_locale_for(None, None), Locale::Sv);
+    }
+
+    #[test]
+    fn missing_messages_are_taken_from_swedish() {
+        let config: Configuration = serde_yaml::from_str(CONFIG).expect("failed to parse");
+
+        assert_eq!(config.get_authors(Locale::En), "Made by\n\n");
+        assert_eq!(
+            config.get_time_report(Locale::En).middle["general"][0].get_text(),
+            "Tidrapportera"
+        );
+        // Only the replies are missing
+        assert_eq!(config.validate().len(), REPLIES.len());
+    }
+
+    #[tokio::test]
+    async fn clients_are_reminded_for_their_period() {
+        let config: Configuration = serde_yaml::from_str(&format!(
+            "{}clients:
+  volvo: {{ period: {{ type: weekly, weekday: Fri }} }}
+  saab: {{ period: {{ type: cutoff, day: 25 }} }}
+  scania: {{ period: {{ type: cutoff, day: 32 }} }}
+payday: {{ day: 0 }}
+client_time_report:
+  weekly:
+    beginning: {{ general: [Hej] }}
+    middle: {{ general: [Rapportera veckan] }}
+    end: {{ general: [Trevlig helg] }}
+",
+            CONFIG
+        ))
+        .expect("failed to parse");
+        let path =
+            std::env::temp_dir().join(format!("joel-bot-clients-{}.json", std::process::id()));
+        let storage = Storage::open_path(path).expect("failed to open");
+        let weekly = ReportingPeriod::Weekly {
+            weekday: Weekday::Fri,
         };
+        let contexts = [String::from("general")];
+        let mut rng = StdRng::seed_from_u64(1);
 
-        let index = random.random_range(0..part.len());
+        assert_eq!(
+            config
+                .preview_message(
+                    Locale::Sv,
+                    &weekly,
+                    &contexts,
+                    &Variables::default(),
+                    &storage,
+                    &mut rng
+                )
+                .await,
+            "<!channel> Hej\nRapportera veckan\nTrevlig helg"
+        );
+        assert!(config.validate().iter().any(|problem| problem.to_string()
+            == "clients.saab.period is cutoff but client_time_report.cutoff is missing"));
+        assert!(config.validate().iter().any(|problem| problem.to_string()
+            == "clients.scania.period.day must be a day of the month, 1-31"));
+        assert!(config
+            .validate()
+            .iter()
+            .any(|problem| problem.to_string() == "payday.day must be a day of the month, 1-31"));
+    }
 
-        let string = &part[index];
+    #[tokio::test]
+    async fn previews_leave_the_history_alone() {
+        let config: Configuration = serde_yaml::from_str(
+            "intro:
+  greetings: [Hej]
+  about_me: Jag är joel-bot
+  features: []
+  credits: { intro: Skapad av, names: [] }
+time_report:
+  beginning: { general: [Hej, Hallå] }
+  middle: { general: [Tidrapportera], 12: [God jul, Gott nytt år] }
+  end: { general: [Hejdå, { text: Aldrig, weight: 0 }] }",
+        )
+        .expect("failed to parse");
+        let path =
+            std::env::temp_dir().join(format!("joel-bot-preview-{}.json", std::process::id()));
+        let storage = Storage::open_path(path.clone()).expect("failed to open");
+        let contexts = [String::from("12"), String::from("general")];
+        let variables = Variables::default();
+        let monthly = ReportingPeriod::Monthly;
 
-        string.clone()
+        let preview = async |seed: u64| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            config
+                .preview_message(
+                    Locale::Sv,
+                    &monthly,
+                    &contexts,
+                    &variables,
+                    &storage,
+                    &mut rng,
+                )
+                .await
+        };
+        assert_eq!(preview(7).await, preview(7).await);
+        assert!(!path.exists());
+
+        let mut all = config
+            .preview_all_messages(Locale::Sv, &monthly, &contexts, &variables, &storage)
+            .await;
+        all.sort();
+        assert_eq!(
+            all,
+            vec![
+                "<!channel> Hallå\nGod jul\nHejdå",
+                "<!channel> Hallå\nGott nytt år\nHejdå",
+                "<!channel> Hej\nGod jul\nHejdå",
+                "<!channel> Hej\nGott nytt år\nHejdå",
+            ]
+        );
+    }
+
+    #[test]
+    fn overrides_must_cover_a_date_or_a_range() {
+        let config: Configuration = serde_yaml::from_str(&format!(
+            "{}working_time:
+  overrides:
+    - {{ date: 2020-12-24, hours: free }}
+    - {{ from: 2020-12-28, to: 2020-12-30, hours: free }}
+    - {{ from: 2020-12-28, hours: free }}
+    - {{ to: 2020-12-30, hours: free }}
+    - {{ hours: free }}
+    - {{ from: 2020-12-30, to: 2020-12-28, hours: free }}
+",
+            CONFIG
+        ))
+        .expect("failed to parse");
+
+        let problems: Vec<String> = config
+            .validate()
+            .iter()
+            .map(|problem| problem.to_string())
+            .filter(|problem| problem.starts_with("working_time"))
+            .collect();
+        assert_eq!(
+            problems,
+            vec![
+                "working_time.overrides.2 needs both from and to",
+                "working_time.overrides.3 needs both from and to",
+                "working_time.overrides.4 needs either date or both from and to",
+                "working_time.overrides.5 has from after to",
+            ]
+        );
     }
 }
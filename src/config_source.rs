@@ -0,0 +1,218 @@
+use std::error::Error;
+use std::path::PathBuf;
+
+use serde_yaml::{Mapping, Value};
+
+use crate::config::{Configuration, CONFIG_PATH};
+
+// Individual keys can be overridden with e.g. `JOEL_BOT__PAYDAY__CHANNEL=lön`
+const OVERRIDE_PREFIX: &str = "JOEL_BOT__";
+const OVERRIDE_SEPARATOR: &str = "__";
+// Maps keyed by Slack user or channel id, e.g. `JOEL_BOT__COUNTRIES__USERS__U012AB3CD=NO`, where
+// the key keeps its case since the ids are upper case
+const ID_MAPS: [&str; 2] = ["users", "channels"];
+
+/// Where the configuration is read from: a base file, an optional file for the environment next
+/// to it, e.g. `config.prod.yaml` when `APP_ENVIRONMENT=prod`, and overrides from the environment.
+#[derive(Debug, Clone)]
+pub struct ConfigSource {
+    base: PathBuf,
+    environment: Option<String>,
+}
+
+impl ConfigSource {
+    pub fn new(base: PathBuf, environment: Option<String>) -> Self {
+        ConfigSource { base, environment }
+    }
+
+    /// Uses `--config=<path>` if given, otherwise `JOEL_BOT_CONFIG` or `config.yaml`.
+    pub fn from_args(args: &[String]) -> Self {
+        let base = args
+            .iter()
+            .find_map(|arg| arg.strip_prefix("--config="))
+            .map(String::from)
+            .or_else(|| std::env::var("JOEL_BOT_CONFIG").ok())
+            .unwrap_or_else(|| String::from(CONFIG_PATH));
+        let environment = std::env::var("APP_ENVIRONMENT")
+            .ok()
+            .filter(|environment| !environment.is_empty());
+
+        ConfigSource::new(PathBuf::from(base), environment)
+    }
+
+    /// The files to read, in the order they are layered.
+    pub fn get_paths(&self) -> Vec<PathBuf> {
+        let mut paths = vec![self.base.clone()];
+        if let Some(path) = self.get_environment_path().filter(|path| path.exists()) {
+            paths.push(path);
+        }
+        paths
+    }
+
+    pub fn get_environment_path(&self) -> Option<PathBuf> {
+        let environment = self.environment.as_ref()?;
+        let extension = self
+            .base
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_string())
+            .unwrap_or_else(|| String::from("yaml"));
+        Some(
+            self.base
+                .with_extension(format!("{}.{}", environment, extension)),
+        )
+    }
+
+    /// Reads all layers without validating the result.
+    pub fn read_value(&self) -> Result<Value, Box<dyn Error>> {
+        let mut value = Value::Mapping(Mapping::new());
+        for path in self.get_paths() {
+            let layer: Value = serde_yaml::from_reader(std::fs::File::open(&path)?)
+                .map_err(|error| format!("{}: {}", path.display(), error))?;
+            merge(&mut value, layer);
+        }
+        apply_overrides(&mut value, std::env::vars());
+        Ok(value)
+    }
+
+    pub fn load(&self) -> Result<Configuration, Box<dyn Error>> {
+        Configuration::from_value(self.read_value()?)
+    }
+}
+
+/// Merges `layer` into `base`, where mappings are merged key by key and everything else replaced.
+pub fn merge(base: &mut Value, layer: Value) {
+    match (base, layer) {
+        (Value::Mapping(base), Value::Mapping(layer)) => {
+            for (key, value) in layer {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, layer) => *base = layer,
+    }
+}
+
+/// Sets the keys of `JOEL_BOT__<KEY>__<KEY>=<value>` variables, where the value is read as YAML.
+/// Keys match existing keys regardless of case, new keys are lower case except in [ID_MAPS].
+pub fn apply_overrides(value: &mut Value, vars: impl Iterator<Item = (String, String)>) {
+    for (name, override_value) in vars {
+        let keys = match name.strip_prefix(OVERRIDE_PREFIX) {
+            Some(keys) => keys,
+            None => continue,
+        };
+        let override_value = serde_yaml::from_str(&override_value)
+            .unwrap_or_else(|_| Value::String(override_value.clone()));
+
+        let mut current = &mut *value;
+        let mut parent: Option<String> = None;
+        for key in keys.split(OVERRIDE_SEPARATOR) {
+            if !current.is_mapping() {
+                *current = Value::Mapping(Mapping::new());
+            }
+            let mapping = current.as_mapping_mut().unwrap();
+            let key = get_override_key(mapping, key, parent.as_deref());
+            parent = Some(key.clone());
+            current = mapping.entry(Value::String(key)).or_insert(Value::Null);
+        }
+        *current = override_value;
+    }
+}
+
+fn get_override_key(mapping: &Mapping, key: &str, parent: Option<&str>) -> String {
+    let existing = mapping
+        .keys()
+        .filter_map(Value::as_str)
+        .find(|existing| existing.eq_ignore_ascii_case(key));
+    match existing {
+        Some(existing) => existing.to_string(),
+        None if parent.is_some_and(|parent| ID_MAPS.contains(&parent)) => key.to_string(),
+        None => key.to_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use serde_yaml::Value;
+
+    use super::{apply_overrides, merge, ConfigSource};
+
+    fn yaml(text: &str) -> Value {
+        serde_yaml::from_str(text).expect("failed to parse")
+    }
+
+    #[test]
+    fn layers_are_merged_key_by_key() {
+        let mut base = yaml("payday:\n  day: 25\n  messages: [a, b]\nintro:\n  about_me: hej");
+        merge(&mut base, yaml("payday:\n  channel: lön\n  messages: [c]"));
+
+        assert_eq!(
+            base,
+            yaml("payday:\n  day: 25\n  channel: lön\n  messages: [c]\nintro:\n  about_me: hej")
+        );
+    }
+
+    #[test]
+    fn single_keys_are_overridden_from_the_environment() {
+        let mut value = yaml("payday:\n  day: 25\nworking_time:\n  hours_per_day: 8");
+        let vars = [
+            ("JOEL_BOT__PAYDAY__DAY", "27"),
+            ("JOEL_BOT__WORKING_TIME__HOURS_PER_DAY", "7.5"),
+            ("JOEL_BOT__COUNTRIES__DEFAULT", "NO"),
+            ("JOEL_BOT_SLACK_TOKEN", "secret"),
+        ];
+        apply_overrides(
+            &mut value,
+            vars.iter()
+                .map(|(name, value)| (name.to_string(), value.to_string())),
+        );
+
+        assert_eq!(
+            value,
+            yaml("payday:\n  day: 27\nworking_time:\n  hours_per_day: 7.5\ncountries:\n  default: \"NO\"")
+        );
+    }
+
+    #[test]
+    fn slack_ids_keep_their_case_when_overridden() {
+        let mut value = yaml("countries:\n  default: SE\n  users:\n    U01: DK\n  channels: {}");
+        let vars = [
+            ("JOEL_BOT__COUNTRIES__USERS__U01", "FI"),
+            ("JOEL_BOT__COUNTRIES__USERS__U02", "NO"),
+            ("JOEL_BOT__COUNTRIES__CHANNELS__C03", "NO"),
+            ("JOEL_BOT__LOCALES__USERS__U02", "en"),
+        ];
+        apply_overrides(
+            &mut value,
+            vars.iter()
+                .map(|(name, value)| (name.to_string(), value.to_string())),
+        );
+
+        assert_eq!(
+            value,
+            yaml("countries:\n  default: SE\n  users:\n    U01: FI\n    U02: \"NO\"\n  channels:\n    C03: \"NO\"\nlocales:\n  users:\n    U02: en")
+        );
+    }
+
+    #[test]
+    fn environment_file_is_next_to_the_base_file() {
+        let source = ConfigSource::new(
+            PathBuf::from("/etc/joel-bot/config.yaml"),
+            Some(String::from("prod")),
+        );
+
+        assert_eq!(
+            source.get_environment_path(),
+            Some(PathBuf::from("/etc/joel-bot/config.prod.yaml"))
+        );
+        assert_eq!(
+            source.get_paths(),
+            vec![PathBuf::from("/etc/joel-bot/config.yaml")]
+        );
+    }
+}
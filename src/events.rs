@@ -1,9 +1,26 @@
-use crate::client::{SlackClient, SlackClientTrait};
+use crate::admin::admin_message;
+use crate::client::{get_user_timezone, SlackClient, SlackClientTrait};
 use crate::config::Configuration;
-use crate::last_day::get_last_workday;
-use chrono::Utc;
+use crate::format::{
+    format_count, format_date, format_hours, generate_formatted_duration, month_name, parse_month,
+};
+use crate::last_day::Calendar;
+use crate::locale::Locale;
+use crate::office_hours::{parse_time_range, Lunch, OfficeHours};
+use crate::replies::{Replies, Reply};
+use crate::reporting::get_deadline;
+use crate::shared_config::SharedConfiguration;
+use crate::storage::Storage;
+use crate::template::Variables;
+use crate::time_off::get_time_off;
+use crate::working_time::get_working_time;
+use chrono::{Datelike, NaiveDate, Utc};
+use rocket::request::{FromRequest, Outcome, Request};
 use serde::Deserialize;
-use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::Arc;
+
+// Items listed per category by `ledigt`
+const MAX_TIME_OFF_ITEMS: usize = 5;
 
 #[derive(Deserialize)]
 #[serde(tag = "type")]
@@ -16,16 +33,11 @@ pub enum SlackRequest {
 
 #[derive(Deserialize)]
 pub struct ChallengeRequest {
-    // TODO: Add if needed
-    token: String,
     pub challenge: String,
 }
 
 #[derive(Deserialize)]
 pub struct EventRequest {
-    // TODO: Add to use when we get request
-    #[allow(dead_code)]
-    token: String,
     pub event: Event,
 }
 
@@ -36,6 +48,21 @@ pub struct AppMentionEvent {
     pub channel: String,
 }
 
+/// Whether Slack is resending an event because it wasn't answered within 3 seconds.
+///
+/// More information here: https://api.slack.com/apis/events-api#retries
+pub struct SlackRetry(pub Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for SlackRetry {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let retry = request.headers().get_one("X-Slack-Retry-Num");
+        Outcome::Success(SlackRetry(retry.map(String::from)))
+    }
+}
+
 #[derive(Deserialize)]
 #[serde(tag = "type")]
 pub enum Event {
@@ -44,79 +71,160 @@ pub enum Event {
 }
 
 pub struct SlackState {
-    token: AtomicPtr<String>,
-    slack_client: SlackClient,
+    slack_client: Arc<SlackClient>,
+    storage: Arc<Storage>,
+    config: Arc<SharedConfiguration>,
 }
 
 impl SlackState {
-    pub fn new() -> Self {
+    pub fn new(
+        slack_client: Arc<SlackClient>,
+        storage: Arc<Storage>,
+        config: Arc<SharedConfiguration>,
+    ) -> Self {
         SlackState {
-            slack_client: SlackClient::new().unwrap(),
-            token: AtomicPtr::new(Box::into_raw(Box::new(String::new()))), // Fixing memory management
+            slack_client,
+            storage,
+            config,
         }
     }
 
     async fn handle_challenge_request(&self, request: ChallengeRequest) -> String {
-        self.token
-            .store(&mut String::from(request.token), Ordering::Relaxed);
         request.challenge
     }
 
-    async fn verify_event_then_call(&self, req: EventRequest) -> String {
-        // TODO: Verify the token then allow the request.
+    /// `verified` is whether Slack's signature was checked, see [`SlackSigned`].
+    ///
+    /// [`SlackSigned`]: crate::signature::SlackSigned
+    async fn handle_event(&self, req: EventRequest, retry: SlackRetry, verified: bool) -> String {
+        // The first delivery is already being answered, answering again would post twice
+        if let Some(retry) = retry.0 {
+            println!("ignoring retry {} of an event", retry);
+            return String::new();
+        }
 
+        // Answers may take several calendar lookups, and Slack retries anything slower than 3 seconds
         match req.event {
             Event::AppMentionEvent(event) => {
-                SlackState::handle_mention_event(&self.slack_client, event).await
+                let client = self.slack_client.clone();
+                let storage = self.storage.clone();
+                let config = self.config.get();
+                tokio::spawn(async move {
+                    SlackState::handle_mention_event(
+                        client.as_ref(),
+                        &storage,
+                        &config,
+                        event,
+                        verified,
+                    )
+                    .await
+                });
+                String::new()
             }
         }
     }
 
-    pub async fn handle_request(&self, request: SlackRequest) -> String {
+    pub async fn handle_request(
+        &self,
+        request: SlackRequest,
+        retry: SlackRetry,
+        verified: bool,
+    ) -> String {
         match request {
             SlackRequest::Challenge(request) => self.handle_challenge_request(request).await,
-            SlackRequest::Event(request) => self.verify_event_then_call(request).await,
+            SlackRequest::Event(request) => self.handle_event(request, retry, verified).await,
         }
     }
 
     async fn handle_mention_event(
         client: &impl SlackClientTrait,
+        storage: &Storage,
+        config: &Configuration,
         event: AppMentionEvent,
+        verified: bool,
     ) -> String {
-        let config = Configuration::read().expect("couldn't read configuration when mentioned");
         let mut splits: Vec<&str> = event.text.split(" ").collect();
         splits.drain(0..1);
 
-        let message: String = if splits.len() > 0 {
+        // Answer with dates as seen by the user, who might be working abroad
+        let timezone = get_user_timezone(client, Some(&event.user)).await;
+        let today = Utc::now().with_timezone(&timezone).date_naive();
+        let locale = config.get_locale_for(Some(&event.user), Some(&event.channel));
+        let replies = config.get_replies(locale);
+
+        let message: String = if !splits.is_empty() {
             match splits[0] {
-                "tid" => {
-                    let today = Utc::now().naive_utc().date();
-                    match get_last_workday(&today).await {
-                        Ok(last_workday) => {
-                            if last_workday == today {
-                                format!("Okej, jag har kikat i kalendern och det är först *{}* som du behöver tidrapportera!\n\n... vänta\n... beräknar\n... det är ju idag!", last_workday)
-                            } else {
-                                format!("Okej, jag har kikat i kalendern och det är först *{}* som du behöver tidrapportera!", last_workday)
-                            }
-                        }
-                        Err(error) => {
-                            println!("{}", error);
-                            String::from("Herregud någonting gick skitfel! Jag kanske behöver uppdatera min firmware :joel:. Kan någon snälla kolla loggen i Azure?")
+                "tid" | "time" => {
+                    let calendar = config.get_calendar_for(Some(&event.user), Some(&event.channel));
+                    match splits.get(1) {
+                        Some(name) => {
+                            SlackState::client_deadline_message(
+                                config, &replies, &calendar, name, &today,
+                            )
+                            .await
                         }
+                        None => SlackState::last_workday_message(&replies, &calendar, &today).await,
                     }
                 }
-                "pricing" => {
-                    String::from("För den nätta kostnaden av 114,805 kr per månad eller 15,8 öre per timme kan du hosta din egen joel-bot! :joel:")
+                "arbetsdagar" | "workdays" => {
+                    let calendar = config.get_calendar_for(Some(&event.user), Some(&event.channel));
+                    match splits.get(1) {
+                        Some(month) => match parse_month(month, &today) {
+                            Some(month) => {
+                                SlackState::working_time_message(&replies, &calendar, &month).await
+                            }
+                            None => replies.get(
+                                Reply::MonthUnknown,
+                                &Variables::default().with("name", month.to_string()),
+                            ),
+                        },
+                        None => SlackState::working_time_message(&replies, &calendar, &today).await,
+                    }
+                }
+                "ledigt" | "time-off" => {
+                    let calendar = config.get_calendar_for(Some(&event.user), Some(&event.channel));
+                    SlackState::time_off_message(&replies, &calendar, &today).await
+                }
+                "gg" => {
+                    SlackState::office_hours_message(
+                        config,
+                        &replies,
+                        storage,
+                        &event.user,
+                        &splits[1..],
+                    )
+                    .await
                 }
-                "skribenter" => {
-                    config.get_authors()
+                "lön" | "payday" => {
+                    let calendar = config.get_calendar_for(Some(&event.user), Some(&event.channel));
+                    SlackState::payday_message(config, &replies, &calendar, &today).await
                 }
+                // Anyone could claim to be an admin in an event that isn't signed
+                "admin" if !verified => replies.get(Reply::AdminUnverified, &Variables::default()),
+                "admin" => {
+                    admin_message(
+                        config,
+                        &replies,
+                        storage,
+                        &event.user,
+                        &splits[1..],
+                        Utc::now(),
+                    )
+                    .await
+                }
+                "pricing" => replies.get(Reply::Pricing, &Variables::default()),
+                "skribenter" | "authors" => config.get_authors(locale),
                 _command => {
-                    format!("Är du skön eller <@{}>? Tror du att _jag_ vet något om *{}*? :joel:", event.user, splits.join(" "))
+                    let variables = Variables::default()
+                        .with_user(&event.user)
+                        .with("command", splits.join(" "));
+                    replies.get(Reply::UnknownCommand, &variables)
                 }
             }
         } else {
-            config.get_introduction()
+            let calendar = config.get_calendar_for(Some(&event.user), Some(&event.channel));
+            let variables = SlackState::get_variables(config, &calendar, &today, locale).await;
+            config.get_introduction(locale, &variables.with_user(&event.user))
         };
         client
             .post_message(&event.channel, &message)
@@ -125,4 +233,311 @@ impl SlackState {
 
         String::new()
     }
+
+    async fn get_variables(
+        config: &Configuration,
+        calendar: &Calendar,
+        today: &NaiveDate,
+        locale: Locale,
+    ) -> Variables {
+        let deadline_time = config.get_working_time().office_hours.end;
+        let variables = match calendar.get_last_workday(today).await {
+            Ok(last_workday) => {
+                Variables::for_deadline(calendar, &last_workday, &deadline_time, today, locale)
+                    .await
+            }
+            Err(error) => Err(error),
+        };
+
+        variables.unwrap_or_else(|error| {
+            println!("failed to get message variables: {}", error);
+            Variables::default()
+        })
+    }
+
+    async fn last_workday_message(
+        replies: &Replies<'_>,
+        calendar: &Calendar,
+        today: &NaiveDate,
+    ) -> String {
+        match calendar.get_last_workday(today).await {
+            Ok(last_workday) => {
+                let variables = Variables::default().with("date", last_workday.to_string());
+                if last_workday == *today {
+                    replies.get(Reply::LastWorkdayToday, &variables)
+                } else {
+                    replies.get(Reply::LastWorkday, &variables)
+                }
+            }
+            Err(error) => {
+                println!("{}", error);
+                replies.get(Reply::Error, &Variables::default())
+            }
+        }
+    }
+
+    async fn client_deadline_message(
+        config: &Configuration,
+        replies: &Replies<'_>,
+        calendar: &Calendar,
+        name: &str,
+        today: &NaiveDate,
+    ) -> String {
+        let client = match config.get_client(name) {
+            Some(client) => client,
+            None => {
+                let mut clients: Vec<&String> = config.get_clients().keys().collect();
+                clients.sort();
+                let clients = clients
+                    .iter()
+                    .map(|client| format!("`{}`", client))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                let variables = Variables::default()
+                    .with("client", name.to_string())
+                    .with("clients", clients);
+                return replies.get(Reply::ClientUnknown, &variables);
+            }
+        };
+
+        match get_deadline(calendar, &client.period, today).await {
+            Ok(deadline) => {
+                let variables = Variables::default()
+                    .with("client", name.to_string())
+                    .with("period", client.period.describe(replies))
+                    .with("date", deadline.to_string());
+                if deadline == *today {
+                    replies.get(Reply::ClientDeadlineToday, &variables)
+                } else {
+                    replies.get(Reply::ClientDeadline, &variables)
+                }
+            }
+            Err(error) => {
+                println!("{}", error);
+                replies.get(Reply::Error, &Variables::default())
+            }
+        }
+    }
+
+    async fn office_hours_message(
+        config: &Configuration,
+        replies: &Replies<'_>,
+        storage: &Storage,
+        user: &str,
+        arguments: &[&str],
+    ) -> String {
+        let current = storage
+            .get_office_hours(user)
+            .await
+            .unwrap_or_else(|| config.get_working_time().office_hours.clone());
+
+        match arguments {
+            [] => {
+                let variables = Variables::default()
+                    .with("start", current.start.format("%H:%M").to_string())
+                    .with("end", current.end.format("%H:%M").to_string());
+                match &current.lunch {
+                    Some(lunch) => {
+                        let lunch = format!(
+                            "{}-{}",
+                            lunch.start.format("%H:%M"),
+                            lunch.end.format("%H:%M")
+                        );
+                        replies.get(Reply::OfficeHours, &variables.with("lunch", lunch))
+                    }
+                    None => replies.get(Reply::OfficeHoursWithoutLunch, &variables),
+                }
+            }
+            ["sätt" | "set", range, rest @ ..] => {
+                let (start, end) = match parse_time_range(range) {
+                    Some(range) => range,
+                    None => {
+                        return replies.get(
+                            Reply::OfficeHoursInvalidRange,
+                            &Variables::default().with("range", range.to_string()),
+                        )
+                    }
+                };
+                let lunch = match rest {
+                    [] => config.get_working_time().office_hours.lunch.clone(),
+                    ["ingen" | "no", "lunch"] => None,
+                    ["lunch", lunch] => match parse_time_range(lunch) {
+                        Some((start, end)) => Some(Lunch { start, end }),
+                        None => {
+                            return replies.get(
+                                Reply::OfficeHoursInvalidLunch,
+                                &Variables::default().with("range", lunch.to_string()),
+                            )
+                        }
+                    },
+                    _ => return replies.get(Reply::OfficeHoursSetUsage, &Variables::default()),
+                };
+                let hours = OfficeHours { start, end, lunch };
+
+                match storage
+                    .update(|data| data.office_hours.insert(String::from(user), hours))
+                    .await
+                {
+                    Ok(_) => {
+                        let variables = Variables::default()
+                            .with("start", start.format("%H:%M").to_string())
+                            .with("end", end.format("%H:%M").to_string());
+                        replies.get(Reply::OfficeHoursSet, &variables)
+                    }
+                    Err(error) => {
+                        println!("failed to store office hours: {}", error);
+                        replies.get(Reply::Error, &Variables::default())
+                    }
+                }
+            }
+            ["återställ" | "reset"] => {
+                match storage.update(|data| data.office_hours.remove(user)).await {
+                    Ok(_) => replies.get(Reply::OfficeHoursReset, &Variables::default()),
+                    Err(error) => {
+                        println!("failed to store office hours: {}", error);
+                        replies.get(Reply::Error, &Variables::default())
+                    }
+                }
+            }
+            _ => replies.get(Reply::OfficeHoursUsage, &Variables::default()),
+        }
+    }
+
+    async fn payday_message(
+        config: &Configuration,
+        replies: &Replies<'_>,
+        calendar: &Calendar,
+        today: &NaiveDate,
+    ) -> String {
+        match get_deadline(calendar, &config.get_payday().get_period(), today).await {
+            Ok(payday) if payday == *today => {
+                replies.get(Reply::PaydayToday, &Variables::default())
+            }
+            Ok(payday) => {
+                let days = (payday - *today).num_days();
+                let variables = Variables::default()
+                    .with("date", payday.to_string())
+                    .with("days", days.to_string());
+                if days == 1 {
+                    replies.get(Reply::PaydayTomorrow, &variables)
+                } else {
+                    replies.get(Reply::PaydayLater, &variables)
+                }
+            }
+            Err(error) => {
+                println!("{}", error);
+                replies.get(Reply::Error, &Variables::default())
+            }
+        }
+    }
+
+    async fn time_off_message(
+        replies: &Replies<'_>,
+        calendar: &Calendar,
+        today: &NaiveDate,
+    ) -> String {
+        let time_off = match get_time_off(calendar, today).await {
+            Ok(time_off) => time_off,
+            Err(error) => {
+                println!("{}", error);
+                return replies.get(Reply::Error, &Variables::default());
+            }
+        };
+        let locale = replies.get_locale();
+        let days = |count: i64| {
+            let (singular, plural) = locale.get_duration_units()[0];
+            format_count(count, singular, plural)
+        };
+        let until = |date: &NaiveDate, workdays_left: usize| {
+            if date == today {
+                return replies.get(Reply::TimeOffToday, &Variables::default());
+            }
+            let (singular, plural) = locale.get_workday_units();
+            let variables = Variables::default()
+                .with(
+                    "duration",
+                    generate_formatted_duration(&(*date - *today), locale),
+                )
+                .with(
+                    "workdays",
+                    format_count(workdays_left as i64, singular, plural),
+                );
+            replies.get(Reply::TimeOffUntil, &variables)
+        };
+        let heading = |reply| format!("\n\n*{}*", replies.get(reply, &Variables::default()));
+
+        let mut message = replies.get(Reply::TimeOff, &Variables::default());
+
+        if !time_off.holidays.is_empty() {
+            message.push_str(&heading(Reply::TimeOffHolidays));
+            for holiday in time_off.holidays.iter().take(MAX_TIME_OFF_ITEMS) {
+                let variables = Variables::default()
+                    .with("name", holiday.name.clone())
+                    .with("date", format_date(&holiday.date, locale))
+                    .with("until", until(&holiday.date, holiday.workdays_left));
+                message.push_str(&format!(
+                    "\n\t- {}",
+                    replies.get(Reply::TimeOffHoliday, &variables)
+                ));
+            }
+        }
+
+        if !time_off.long_weekends.is_empty() {
+            message.push_str(&heading(Reply::TimeOffLongWeekends));
+            for weekend in time_off.long_weekends.iter().take(MAX_TIME_OFF_ITEMS) {
+                let variables = Variables::default()
+                    .with("from", format_date(&weekend.from, locale))
+                    .with("to", format_date(&weekend.to, locale))
+                    .with("days", days((weekend.to - weekend.from).num_days() + 1))
+                    .with("until", until(&weekend.from, weekend.workdays_left));
+                message.push_str(&format!(
+                    "\n\t- {}",
+                    replies.get(Reply::TimeOffLongWeekend, &variables)
+                ));
+            }
+        }
+
+        if !time_off.squeeze_days.is_empty() {
+            message.push_str(&heading(Reply::TimeOffSqueezeDays));
+            for day in time_off.squeeze_days.iter().take(MAX_TIME_OFF_ITEMS) {
+                let variables = Variables::default()
+                    .with("date", format_date(&day.date, locale))
+                    .with("days", days(day.days_off as i64))
+                    .with("until", until(&day.date, day.workdays_left));
+                message.push_str(&format!(
+                    "\n\t- {}",
+                    replies.get(Reply::TimeOffSqueezeDay, &variables)
+                ));
+            }
+        }
+
+        message
+    }
+
+    async fn working_time_message(
+        replies: &Replies<'_>,
+        calendar: &Calendar,
+        month: &NaiveDate,
+    ) -> String {
+        match get_working_time(calendar, month).await {
+            Ok(summary) => {
+                let locale = replies.get_locale();
+                let variables = Variables::default()
+                    .with("month_name", month_name(month.month(), locale).to_string())
+                    .with("year", month.year().to_string())
+                    .with("workdays", summary.workdays.to_string())
+                    .with("half_days", summary.half_days.to_string())
+                    .with("hours", format_hours(summary.hours, locale));
+                match summary.half_days {
+                    0 => replies.get(Reply::WorkingTime, &variables),
+                    1 => replies.get(Reply::WorkingTimeHalfDay, &variables),
+                    _ => replies.get(Reply::WorkingTimeHalfDays, &variables),
+                }
+            }
+            Err(error) => {
+                println!("{}", error);
+                replies.get(Reply::Error, &Variables::default())
+            }
+        }
+    }
 }
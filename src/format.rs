@@ -0,0 +1,154 @@
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+use crate::locale::{Locale, LOCALES};
+
+pub fn month_name(month: u32, locale: Locale) -> &'static str {
+    locale.get_month_names()[(month as usize - 1) % 12]
+}
+
+pub fn weekday_name(weekday: &Weekday, locale: Locale) -> &'static str {
+    locale.get_weekday_name(weekday)
+}
+
+/// Formats a date for humans, e.g. "tisdag 6 januari" or "Tuesday 6 January".
+pub fn format_date(date: &NaiveDate, locale: Locale) -> String {
+    format!(
+        "{} {} {}",
+        weekday_name(&date.weekday(), locale),
+        date.day(),
+        month_name(date.month(), locale)
+    )
+}
+
+/// Formats a count with the singular or plural form of its unit, e.g. "1 timme" or "2 timmar".
+pub fn format_count(count: i64, singular: &str, plural: &str) -> String {
+    if count == 1 {
+        format!("{} {}", count, singular)
+    } else {
+        format!("{} {}", count, plural)
+    }
+}
+
+/// Formats a duration using its two most significant units, e.g. "2 timmar och 5 minuter" or
+/// "3 days".
+pub fn generate_formatted_duration(duration: &Duration, locale: Locale) -> String {
+    let counts = [
+        duration.num_days(),
+        duration.num_hours() % 24,
+        duration.num_minutes() % 60,
+        duration.num_seconds() % 60,
+    ];
+    let units: Vec<(i64, (&str, &str))> = counts
+        .into_iter()
+        .zip(locale.get_duration_units())
+        .collect();
+
+    let first = units
+        .iter()
+        .position(|(count, _)| *count > 0)
+        .unwrap_or(units.len() - 1);
+
+    units[first..]
+        .iter()
+        .take(2)
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, (singular, plural))| format_count(*count, singular, plural))
+        .collect::<Vec<String>>()
+        .join(locale.get_conjunction())
+}
+
+/// Parses a month as written in a Slack message, e.g. `mars`, `march`, `3` or `2020-03`, into the
+/// first day of that month. Months without a year are assumed to be in the year of `today`.
+pub fn parse_month(text: &str, today: &NaiveDate) -> Option<NaiveDate> {
+    let text = text.trim().to_lowercase();
+
+    if let Ok(date) = NaiveDate::parse_from_str(format!("{}-01", text).as_str(), "%Y-%m-%d") {
+        return Some(date);
+    }
+
+    let month = match text.parse::<u32>() {
+        Ok(month) => month,
+        Err(_) => {
+            LOCALES.iter().find_map(|locale| {
+                locale.get_month_names().iter().position(|name| {
+                    let name = name.to_lowercase();
+                    name == text || (text.len() >= 3 && name.starts_with(&text))
+                })
+            })? as u32
+                + 1
+        }
+    };
+
+    NaiveDate::from_ymd_opt(today.year(), month, 1)
+}
+
+/// Formats hours with the decimal separator of `locale` and without trailing zeroes.
+pub fn format_hours(hours: f64, locale: Locale) -> String {
+    format!("{:.2}", hours)
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .replace('.', &locale.get_decimal_separator().to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+
+    use chrono::Duration;
+
+    use super::{format_date, format_hours, generate_formatted_duration, parse_month};
+    use crate::locale::Locale;
+
+    #[test]
+    fn months_are_parsed_from_names_numbers_and_dates() {
+        let today = NaiveDate::from_ymd_opt(2020, 10, 15).unwrap();
+        let march = NaiveDate::from_ymd_opt(2020, 3, 1).unwrap();
+
+        assert_eq!(parse_month("mars", &today), Some(march));
+        assert_eq!(parse_month("Mar", &today), Some(march));
+        assert_eq!(parse_month("3", &today), Some(march));
+        assert_eq!(
+            parse_month("2021-03", &today),
+            NaiveDate::from_ymd_opt(2021, 3, 1)
+        );
+        assert_eq!(parse_month("13", &today), None);
+        assert_eq!(parse_month("ma", &today), None);
+        assert_eq!(
+            parse_month("October", &today),
+            NaiveDate::from_ymd_opt(2020, 10, 1)
+        );
+        assert_eq!(parse_month("okt", &today), parse_month("oct", &today));
+    }
+
+    #[test]
+    fn hours_use_the_decimal_separator_of_the_locale() {
+        assert_eq!(format_hours(164.0, Locale::Sv), "164");
+        assert_eq!(format_hours(3.5, Locale::Sv), "3,5");
+        assert_eq!(format_hours(161.25, Locale::Sv), "161,25");
+        assert_eq!(format_hours(161.25, Locale::En), "161.25");
+    }
+
+    #[test]
+    fn durations_use_two_most_significant_units() {
+        let format = |seconds| generate_formatted_duration(&Duration::seconds(seconds), Locale::Sv);
+
+        assert_eq!(format(1), "1 sekund");
+        assert_eq!(format(61), "1 minut och 1 sekund");
+        assert_eq!(format(2 * 3600 + 5 * 60 + 7), "2 timmar och 5 minuter");
+        assert_eq!(format(3600 + 7), "1 timme");
+        assert_eq!(format(3 * 86400 + 60), "3 dagar");
+        assert_eq!(format(86400 + 3600), "1 dag och 1 timme");
+    }
+
+    #[test]
+    fn durations_and_dates_in_english() {
+        let format = |seconds| generate_formatted_duration(&Duration::seconds(seconds), Locale::En);
+
+        assert_eq!(format(61), "1 minute and 1 second");
+        assert_eq!(format(2 * 86400 + 13 * 3600), "2 days and 13 hours");
+        assert_eq!(
+            format_date(&NaiveDate::from_ymd_opt(2026, 1, 6).unwrap(), Locale::En),
+            "Tuesday 6 January"
+        );
+    }
+}
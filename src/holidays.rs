@@ -0,0 +1,194 @@
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use serde::Deserialize;
+
+/// Countries with offline holiday rules.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Country {
+    #[default]
+    SE,
+    NO,
+    DK,
+    FI,
+}
+
+/// Returns the work free holidays of `country` in `year`, sorted by date.
+///
+/// Besides the official public holidays this includes the eves that are work free in practice,
+/// e.g. midsommarafton and julafton in Sweden.
+pub fn get_public_holidays(country: Country, year: i32) -> Vec<(NaiveDate, &'static str)> {
+    let date = |month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+    let easter = get_easter_sunday(year);
+    let from_easter = |days: i64| easter + chrono::Duration::days(days);
+
+    let mut holidays = match country {
+        Country::SE => vec![
+            (date(1, 1), "Nyårsdagen"),
+            (date(1, 6), "Trettondedag jul"),
+            (from_easter(-2), "Långfredagen"),
+            (easter, "Påskdagen"),
+            (from_easter(1), "Annandag påsk"),
+            (date(5, 1), "Första maj"),
+            (from_easter(39), "Kristi himmelsfärdsdag"),
+            (from_easter(49), "Pingstdagen"),
+            (date(6, 6), "Sveriges nationaldag"),
+            (
+                first_weekday_from(date(6, 19), Weekday::Fri),
+                "Midsommarafton",
+            ),
+            (
+                first_weekday_from(date(6, 20), Weekday::Sat),
+                "Midsommardagen",
+            ),
+            (
+                first_weekday_from(date(10, 31), Weekday::Sat),
+                "Alla helgons dag",
+            ),
+            (date(12, 24), "Julafton"),
+            (date(12, 25), "Juldagen"),
+            (date(12, 26), "Annandag jul"),
+            (date(12, 31), "Nyårsafton"),
+        ],
+        Country::NO => vec![
+            (date(1, 1), "Første nyttårsdag"),
+            (from_easter(-3), "Skjærtorsdag"),
+            (from_easter(-2), "Langfredag"),
+            (easter, "Første påskedag"),
+            (from_easter(1), "Andre påskedag"),
+            (date(5, 1), "Arbeidernes dag"),
+            (date(5, 17), "Grunnlovsdag"),
+            (from_easter(39), "Kristi himmelfartsdag"),
+            (from_easter(49), "Første pinsedag"),
+            (from_easter(50), "Andre pinsedag"),
+            (date(12, 25), "Første juledag"),
+            (date(12, 26), "Andre juledag"),
+        ],
+        Country::DK => {
+            let mut holidays = vec![
+                (date(1, 1), "Nytårsdag"),
+                (from_easter(-3), "Skærtorsdag"),
+                (from_easter(-2), "Langfredag"),
+                (easter, "Påskedag"),
+                (from_easter(1), "2. påskedag"),
+                (from_easter(39), "Kristi himmelfartsdag"),
+                (from_easter(49), "Pinsedag"),
+                (from_easter(50), "2. pinsedag"),
+                (date(6, 5), "Grundlovsdag"),
+                (date(12, 24), "Juleaften"),
+                (date(12, 25), "Juledag"),
+                (date(12, 26), "2. juledag"),
+            ];
+            // Store bededag was abolished as a public holiday from 2024
+            if year < 2024 {
+                holidays.push((from_easter(26), "Store bededag"));
+            }
+            holidays
+        }
+        Country::FI => vec![
+            (date(1, 1), "Uudenvuodenpäivä"),
+            (date(1, 6), "Loppiainen"),
+            (from_easter(-2), "Pitkäperjantai"),
+            (easter, "Pääsiäispäivä"),
+            (from_easter(1), "2. pääsiäispäivä"),
+            (date(5, 1), "Vappu"),
+            (from_easter(39), "Helatorstai"),
+            (from_easter(49), "Helluntaipäivä"),
+            (
+                first_weekday_from(date(6, 19), Weekday::Fri),
+                "Juhannusaatto",
+            ),
+            (
+                first_weekday_from(date(6, 20), Weekday::Sat),
+                "Juhannuspäivä",
+            ),
+            (
+                first_weekday_from(date(10, 31), Weekday::Sat),
+                "Pyhäinpäivä",
+            ),
+            (date(12, 6), "Itsenäisyyspäivä"),
+            (date(12, 24), "Jouluaatto"),
+            (date(12, 25), "Joulupäivä"),
+            (date(12, 26), "Tapaninpäivä"),
+        ],
+    };
+
+    holidays.sort_by_key(|(date, _)| *date);
+    holidays
+}
+
+/// Easter Sunday in the Gregorian calendar, using the anonymous Gregorian algorithm.
+pub fn get_easter_sunday(year: i32) -> NaiveDate {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32).unwrap()
+}
+
+pub fn first_weekday_from(date: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let offset = (7 + weekday.num_days_from_monday() - date.weekday().num_days_from_monday()) % 7;
+    date.checked_add_days(Days::new(offset as u64)).unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+
+    use super::{get_easter_sunday, get_public_holidays, Country};
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn easter_sunday() {
+        assert_eq!(get_easter_sunday(2020), date(2020, 4, 12));
+        assert_eq!(get_easter_sunday(2024), date(2024, 3, 31));
+        assert_eq!(get_easter_sunday(2025), date(2025, 4, 20));
+        assert_eq!(get_easter_sunday(2038), date(2038, 4, 25));
+    }
+
+    #[test]
+    fn swedish_midsummer_and_all_saints() {
+        let holidays = get_public_holidays(Country::SE, 2020);
+
+        assert!(holidays.contains(&(date(2020, 6, 19), "Midsommarafton")));
+        assert!(holidays.contains(&(date(2020, 6, 20), "Midsommardagen")));
+        assert!(holidays.contains(&(date(2020, 10, 31), "Alla helgons dag")));
+        assert!(holidays.contains(&(date(2020, 5, 21), "Kristi himmelsfärdsdag")));
+    }
+
+    #[test]
+    fn norwegian_constitution_day_and_danish_prayer_day() {
+        let norway = get_public_holidays(Country::NO, 2020);
+        assert!(norway.contains(&(date(2020, 5, 17), "Grunnlovsdag")));
+        assert!(norway.contains(&(date(2020, 4, 9), "Skjærtorsdag")));
+
+        let denmark_2023 = get_public_holidays(Country::DK, 2023);
+        let denmark_2024 = get_public_holidays(Country::DK, 2024);
+        assert!(denmark_2023
+            .iter()
+            .any(|(_, name)| *name == "Store bededag"));
+        assert!(!denmark_2024
+            .iter()
+            .any(|(_, name)| *name == "Store bededag"));
+    }
+
+    #[test]
+    fn finnish_independence_day() {
+        let holidays = get_public_holidays(Country::FI, 2020);
+
+        assert!(holidays.contains(&(date(2020, 12, 6), "Itsenäisyyspäivä")));
+        assert!(holidays.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+    }
+}
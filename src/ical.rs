@@ -0,0 +1,179 @@
+use chrono::{DateTime, Datelike, Days, NaiveDate, Utc};
+
+use crate::format::month_name;
+use crate::replies::{Replies, Reply};
+use crate::template::Variables;
+
+// More information here: https://datatracker.ietf.org/doc/html/rfc5545
+const PRODUCT_ID: &str = "-//Magello//joel-bot";
+const MAX_LINE_LENGTH: usize = 75;
+
+/// Builds an iCalendar feed with one all-day event per time report deadline, in the locale of
+/// `replies`.
+///
+/// Every event carries two reminders, one at 09:00 the day before and one at 09:00 on the
+/// deadline itself.
+pub fn time_report_calendar(
+    deadlines: &[NaiveDate],
+    generated_at: &DateTime<Utc>,
+    replies: &Replies,
+) -> String {
+    let mut lines = vec![
+        String::from("BEGIN:VCALENDAR"),
+        String::from("VERSION:2.0"),
+        format!(
+            "PRODID:{}//{}",
+            PRODUCT_ID,
+            replies.get_locale().get_code().to_uppercase()
+        ),
+        String::from("CALSCALE:GREGORIAN"),
+        String::from("METHOD:PUBLISH"),
+        format!(
+            "X-WR-CALNAME:{}",
+            escape_text(&replies.get(Reply::CalendarName, &Variables::default()))
+        ),
+        String::from("X-WR-TIMEZONE:Europe/Stockholm"),
+        String::from("REFRESH-INTERVAL;VALUE=DURATION:P1D"),
+        String::from("X-PUBLISHED-TTL:P1D"),
+    ];
+
+    for deadline in deadlines {
+        lines.extend(deadline_event(deadline, generated_at, replies));
+    }
+
+    lines.push(String::from("END:VCALENDAR"));
+
+    lines
+        .iter()
+        .map(|line| fold_line(line))
+        .map(|line| format!("{}\r\n", line))
+        .collect()
+}
+
+fn deadline_event(
+    deadline: &NaiveDate,
+    generated_at: &DateTime<Utc>,
+    replies: &Replies,
+) -> Vec<String> {
+    let next_day = deadline.checked_add_days(Days::new(1)).unwrap();
+    let text = |reply| escape_text(&replies.get(reply, &Variables::default()));
+    let variables = Variables::default().with(
+        "month_name",
+        month_name(deadline.month(), replies.get_locale()).to_string(),
+    );
+    let description = replies.get(Reply::CalendarDescription, &variables);
+
+    vec![
+        String::from("BEGIN:VEVENT"),
+        format!("UID:time-report-{}@joel-bot", deadline.format("%Y%m%d")),
+        format!("DTSTAMP:{}", generated_at.format("%Y%m%dT%H%M%SZ")),
+        format!("DTSTART;VALUE=DATE:{}", deadline.format("%Y%m%d")),
+        format!("DTEND;VALUE=DATE:{}", next_day.format("%Y%m%d")),
+        format!("SUMMARY:{}", text(Reply::CalendarSummary)),
+        format!("DESCRIPTION:{}", escape_text(&description)),
+        String::from("TRANSP:TRANSPARENT"),
+        String::from("BEGIN:VALARM"),
+        String::from("ACTION:DISPLAY"),
+        format!("DESCRIPTION:{}", text(Reply::CalendarAlarmDayBefore)),
+        String::from("TRIGGER:-PT15H"),
+        String::from("END:VALARM"),
+        String::from("BEGIN:VALARM"),
+        String::from("ACTION:DISPLAY"),
+        format!("DESCRIPTION:{}", text(Reply::CalendarAlarmToday)),
+        String::from("TRIGGER:PT9H"),
+        String::from("END:VALARM"),
+        String::from("END:VEVENT"),
+    ]
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+// Lines longer than 75 octets must be split, with every continuation line starting with a space.
+fn fold_line(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+
+    for character in line.chars() {
+        if length + character.len_utf8() > MAX_LINE_LENGTH {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(character);
+        length += character.len_utf8();
+    }
+
+    folded
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    use super::{escape_text, fold_line, time_report_calendar};
+    use crate::config::Configuration;
+    use crate::locale::Locale;
+
+    fn config() -> Configuration {
+        let value =
+            serde_yaml::from_str(&std::fs::read_to_string("config.yaml").unwrap()).expect("failed");
+        Configuration::parse_value(value).expect("failed")
+    }
+
+    #[test]
+    fn calendar_contains_one_event_per_deadline() {
+        let deadlines = [
+            NaiveDate::from_ymd_opt(2020, 10, 30).unwrap(),
+            NaiveDate::from_ymd_opt(2020, 11, 30).unwrap(),
+        ];
+        let generated_at = Utc.with_ymd_and_hms(2020, 10, 1, 12, 0, 0).unwrap();
+
+        let config = config();
+        let calendar =
+            time_report_calendar(&deadlines, &generated_at, &config.get_replies(Locale::Sv));
+
+        assert!(calendar.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(calendar.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(calendar.matches("BEGIN:VEVENT").count(), 2);
+        assert_eq!(calendar.matches("BEGIN:VALARM").count(), 4);
+        assert!(calendar.contains("DTSTART;VALUE=DATE:20201030\r\n"));
+        assert!(calendar.contains("DTEND;VALUE=DATE:20201031\r\n"));
+        assert!(calendar.contains("DTSTAMP:20201001T120000Z\r\n"));
+        assert!(calendar.contains("X-WR-CALNAME:Tidrapportering\r\n"));
+    }
+
+    #[test]
+    fn calendar_is_in_the_locale_of_the_replies() {
+        let deadlines = [NaiveDate::from_ymd_opt(2020, 10, 30).unwrap()];
+        let generated_at = Utc.with_ymd_and_hms(2020, 10, 1, 12, 0, 0).unwrap();
+
+        let config = config();
+        let calendar =
+            time_report_calendar(&deadlines, &generated_at, &config.get_replies(Locale::En));
+
+        assert!(calendar.contains("PRODID:-//Magello//joel-bot//EN\r\n"));
+        assert!(calendar.contains("X-WR-CALNAME:Time reporting\r\n"));
+        assert!(calendar.contains("SUMMARY:Report your time!\r\n"));
+        assert!(calendar.contains("the last workday of October"));
+    }
+
+    #[test]
+    fn long_lines_are_folded() {
+        let line = "D".repeat(160);
+        let folded = fold_line(&line);
+
+        for part in folded.split("\r\n") {
+            assert!(part.len() <= 75);
+        }
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+
+    #[test]
+    fn text_is_escaped() {
+        assert_eq!(escape_text("a, b; c\nd"), "a\\, b\\; c\\nd");
+    }
+}
@@ -1,7 +1,22 @@
-use anyhow::Result;
-use chrono::{Datelike, NaiveDate};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Months, NaiveDate, Weekday};
 use reqwest::Client;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{CalendarOverride, DayRule, WorkingTime};
+use crate::holidays::{get_public_holidays, Country};
+
+// Holidays are known long in advance, so a fetched month is reused for a day before asking again
+const SHOLIDAY_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+type MonthCache = HashMap<(i32, u32), (Instant, Vec<CalendarDay>)>;
+
+// Months fetched from sholiday by (year, month), shared by every calendar
+static SHOLIDAY_CACHE: LazyLock<Mutex<MonthCache>> = LazyLock::new(Default::default);
 
 #[derive(Deserialize)]
 struct SholidayFaboulResponse {
@@ -20,13 +35,274 @@ struct SholidayFaboulDay {
     date: String,
     #[serde(alias = "arbetsfri dag")]
     work_free_day: String,
+    #[serde(alias = "helgdag")]
+    holiday: Option<String>,
+    // Eves like midsommarafton aren't public holidays, but are work free and have names
+    #[serde(alias = "helgdagsafton")]
+    eve: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkingHours {
+    Full,
+    Half,
+    Free,
+}
+
+/// A single day in the calendar with the configured working hours applied.
+#[derive(Debug, Clone)]
+pub struct CalendarDay {
+    pub date: NaiveDate,
+    pub hours: WorkingHours,
+    /// Name of the public holiday, e.g. "Trettondedag jul"
+    pub holiday: Option<String>,
+    /// Why the company calendar overrides this day, e.g. "Klämdag"
+    pub reason: Option<String>,
+}
+
+impl CalendarDay {
+    pub fn is_workday(&self) -> bool {
+        self.hours != WorkingHours::Free
+    }
+}
+
+/// Public holidays from the holiday provider, layered with the configured working hours.
+#[derive(Default)]
+pub struct Calendar {
+    working_time: WorkingTime,
+    country: Country,
 }
 
 pub async fn is_last_workday(date: &NaiveDate) -> Result<bool> {
-    Ok(get_last_workday(date).await? == *date)
+    Calendar::default().is_last_workday(date).await
 }
 
 pub async fn get_last_workday(date: &NaiveDate) -> Result<NaiveDate> {
+    Calendar::default().get_last_workday(date).await
+}
+
+impl Calendar {
+    pub fn new(working_time: WorkingTime, country: Country) -> Self {
+        Calendar {
+            working_time,
+            country,
+        }
+    }
+
+    pub fn get_country(&self) -> Country {
+        self.country
+    }
+
+    /// Number of hours worked on `day`.
+    pub fn get_hours(&self, day: &CalendarDay) -> f64 {
+        match day.hours {
+            WorkingHours::Full => self.working_time.hours_per_day,
+            WorkingHours::Half => self.working_time.hours_per_day / 2.0,
+            WorkingHours::Free => 0.0,
+        }
+    }
+
+    pub async fn get_day(&self, date: &NaiveDate) -> Result<CalendarDay> {
+        self.get_month(date)
+            .await?
+            .into_iter()
+            .find(|day| day.date == *date)
+            .ok_or_else(|| anyhow!("{} is missing from the calendar", date))
+    }
+
+    pub async fn is_workday(&self, date: &NaiveDate) -> Result<bool> {
+        Ok(self.get_day(date).await?.is_workday())
+    }
+
+    pub async fn is_last_workday(&self, date: &NaiveDate) -> Result<bool> {
+        Ok(self.get_last_workday(date).await? == *date)
+    }
+
+    pub async fn get_last_workday(&self, date: &NaiveDate) -> Result<NaiveDate> {
+        let days = self.get_month(date).await?;
+
+        let last_work_day = days
+            .iter()
+            .rfind(|day| day.is_workday())
+            .ok_or_else(|| anyhow!("no workday found in {}", date.format("%Y-%m")))?;
+
+        Ok(last_work_day.date)
+    }
+
+    /// Returns the first workday after `date`.
+    pub async fn get_next_workday(&self, date: &NaiveDate) -> Result<NaiveDate> {
+        let from = date.succ_opt().unwrap();
+        let days = self.get_days(&from, &(from + Months::new(1))).await?;
+
+        days.iter()
+            .find(|day| day.is_workday())
+            .map(|day| day.date)
+            .ok_or_else(|| anyhow!("no workday found within a month after {}", date))
+    }
+
+    /// Returns the last workday for each of the `months` months starting with the month of `from`.
+    pub async fn get_last_workdays(&self, from: &NaiveDate, months: u32) -> Result<Vec<NaiveDate>> {
+        let mut last_workdays = Vec::new();
+        let mut month = from.with_day(1).unwrap();
+
+        for _ in 0..months {
+            last_workdays.push(self.get_last_workday(&month).await?);
+            month = month + Months::new(1);
+        }
+
+        Ok(last_workdays)
+    }
+
+    /// Returns all workdays between `from` and `to`, both inclusive.
+    pub async fn get_workdays(&self, from: &NaiveDate, to: &NaiveDate) -> Result<Vec<NaiveDate>> {
+        Ok(self
+            .get_days(from, to)
+            .await?
+            .iter()
+            .filter(|day| day.is_workday())
+            .map(|day| day.date)
+            .collect())
+    }
+
+    /// Returns all days between `from` and `to`, both inclusive.
+    pub async fn get_days(&self, from: &NaiveDate, to: &NaiveDate) -> Result<Vec<CalendarDay>> {
+        let mut days = Vec::new();
+        let mut month = from.with_day(1).unwrap();
+
+        while month <= *to {
+            let month_days = self.get_month(&month).await?;
+            days.extend(
+                month_days
+                    .into_iter()
+                    .filter(|day| day.date >= *from && day.date <= *to),
+            );
+            month = month + Months::new(1);
+        }
+
+        Ok(days)
+    }
+
+    /// Returns every day in the month of `date`.
+    pub async fn get_month(&self, date: &NaiveDate) -> Result<Vec<CalendarDay>> {
+        let first_day = date.with_day(1).unwrap();
+        let mut days = get_holidays(self.country, &first_day).await?;
+
+        // The first day of the next month is needed to tell if the last day is before a holiday
+        let has_before_rules = self
+            .working_time
+            .days
+            .iter()
+            .any(|rule| rule.before.is_some());
+        if has_before_rules {
+            days.extend(get_holidays(self.country, &(first_day + Months::new(1))).await?);
+        }
+
+        apply_rules(&mut days, &self.working_time.days);
+        apply_overrides(&mut days, &self.working_time.overrides);
+        days.retain(|day| day.date.month() == first_day.month());
+
+        Ok(days)
+    }
+}
+
+fn apply_rules(days: &mut [CalendarDay], rules: &[DayRule]) {
+    for index in 0..days.len() {
+        let next_holiday = days.get(index + 1).and_then(|next| next.holiday.clone());
+        let day = &mut days[index];
+
+        // Working hours are never added to days that are already free
+        if !day.is_workday() {
+            continue;
+        }
+
+        let month_day = day.date.format("%m-%d").to_string();
+        let rule = rules.iter().find(|rule| {
+            let before = match (&rule.before, &next_holiday) {
+                (Some(before), Some(holiday)) => before.eq_ignore_ascii_case(holiday),
+                _ => false,
+            };
+            before || rule.date.as_deref() == Some(month_day.as_str())
+        });
+
+        if let Some(rule) = rule {
+            day.hours = rule.hours;
+        }
+    }
+}
+
+// Unlike the recurring rules, overrides may also turn weekends and holidays into workdays
+fn apply_overrides(days: &mut [CalendarDay], overrides: &[CalendarOverride]) {
+    for day in days.iter_mut() {
+        if let Some(company_day) = overrides
+            .iter()
+            .rfind(|company_day| company_day.contains(&day.date))
+        {
+            day.hours = company_day.hours;
+            day.reason = company_day.reason.clone();
+        }
+    }
+}
+
+async fn get_holidays(country: Country, date: &NaiveDate) -> Result<Vec<CalendarDay>> {
+    match country {
+        Country::SE => match get_cached_sholiday_month(date).await {
+            Ok(days) => Ok(days),
+            Err(error) => {
+                println!("sholiday failed, falling back to offline rules: {}", error);
+                Ok(get_offline_month(country, date))
+            }
+        },
+        _ => Ok(get_offline_month(country, date)),
+    }
+}
+
+fn get_offline_month(country: Country, date: &NaiveDate) -> Vec<CalendarDay> {
+    let holidays = get_public_holidays(country, date.year());
+
+    date.with_day(1)
+        .unwrap()
+        .iter_days()
+        .take_while(|day| day.month() == date.month())
+        .map(|day| {
+            let holiday = holidays
+                .iter()
+                .find(|(holiday, _)| *holiday == day)
+                .map(|(_, name)| String::from(*name));
+            let weekend = matches!(day.weekday(), Weekday::Sat | Weekday::Sun);
+
+            CalendarDay {
+                date: day,
+                hours: if weekend || holiday.is_some() {
+                    WorkingHours::Free
+                } else {
+                    WorkingHours::Full
+                },
+                holiday,
+                reason: None,
+            }
+        })
+        .collect()
+}
+
+async fn get_cached_sholiday_month(date: &NaiveDate) -> Result<Vec<CalendarDay>> {
+    let key = (date.year(), date.month());
+    if let Some((fetched_at, days)) = SHOLIDAY_CACHE.lock().unwrap().get(&key) {
+        if fetched_at.elapsed() < SHOLIDAY_CACHE_TTL {
+            return Ok(days.clone());
+        }
+    }
+
+    // Failures aren't cached, so the next request tries again
+    let days = get_sholiday_month(date).await?;
+    SHOLIDAY_CACHE
+        .lock()
+        .unwrap()
+        .insert(key, (Instant::now(), days.clone()));
+    Ok(days)
+}
+
+async fn get_sholiday_month(date: &NaiveDate) -> Result<Vec<CalendarDay>> {
     let client = Client::new();
     let url = format!(
         "https://sholiday.faboul.se/dagar/v2.1/{}/{}",
@@ -36,24 +312,42 @@ pub async fn get_last_workday(date: &NaiveDate) -> Result<NaiveDate> {
 
     let response: SholidayFaboulResponse = client.get(url.as_str()).send().await?.json().await?;
 
-    let last_work_day = response
+    get_sholiday_days(&response)
+}
+
+fn get_sholiday_days(response: &SholidayFaboulResponse) -> Result<Vec<CalendarDay>> {
+    response
         .days
         .iter()
-        .rfind(|day| day.work_free_day == "Nej")
-        .unwrap();
-
-    let sholiday_day_date =
-        NaiveDate::parse_from_str(last_work_day.date.clone().as_str(), "%Y-%m-%d")?;
-
-    Ok(sholiday_day_date)
+        .map(|day| {
+            Ok(CalendarDay {
+                date: NaiveDate::parse_from_str(day.date.as_str(), "%Y-%m-%d")?,
+                hours: if day.work_free_day == "Nej" {
+                    WorkingHours::Full
+                } else {
+                    WorkingHours::Free
+                },
+                holiday: day.holiday.clone().or_else(|| day.eve.clone()),
+                reason: None,
+            })
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod test {
-    use chrono::{NaiveDate, TimeZone, Utc};
+    use std::time::Instant;
+
+    use chrono::{Datelike, NaiveDate, TimeZone, Utc};
     use tokio;
 
-    use super::{get_last_workday, is_last_workday};
+    use super::{
+        apply_overrides, apply_rules, get_holidays, get_last_workday, get_offline_month,
+        get_sholiday_days, is_last_workday, CalendarDay, SholidayFaboulResponse, WorkingHours,
+        SHOLIDAY_CACHE,
+    };
+    use crate::config::{CalendarOverride, DayRule};
+    use crate::holidays::Country;
 
     #[tokio::test]
     async fn is_2020_10_31_last_work_day() {
@@ -91,4 +385,160 @@ mod test {
             .date_naive();
         assert_eq!(last_workday, expected_last_workday);
     }
+
+    fn day(date: &str, hours: WorkingHours, holiday: Option<&str>) -> CalendarDay {
+        CalendarDay {
+            date: NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap(),
+            hours,
+            holiday: holiday.map(String::from),
+            reason: None,
+        }
+    }
+
+    #[test]
+    fn rules_set_working_hours() {
+        let mut days = vec![
+            day("2020-04-09", WorkingHours::Full, None),
+            day("2020-04-10", WorkingHours::Free, Some("Långfredagen")),
+            day("2020-04-11", WorkingHours::Free, None),
+            day("2020-04-30", WorkingHours::Full, None),
+            day("2020-05-01", WorkingHours::Free, Some("Första maj")),
+        ];
+        let rules = vec![
+            DayRule {
+                before: Some(String::from("långfredagen")),
+                date: None,
+                hours: WorkingHours::Half,
+            },
+            DayRule {
+                before: None,
+                date: Some(String::from("04-30")),
+                hours: WorkingHours::Free,
+            },
+            DayRule {
+                before: None,
+                date: Some(String::from("04-11")),
+                hours: WorkingHours::Full,
+            },
+        ];
+
+        apply_rules(&mut days, &rules);
+
+        let hours: Vec<WorkingHours> = days.iter().map(|day| day.hours).collect();
+        assert_eq!(
+            hours,
+            vec![
+                WorkingHours::Half,
+                WorkingHours::Free,
+                WorkingHours::Free,
+                WorkingHours::Free,
+                WorkingHours::Free,
+            ]
+        );
+    }
+
+    #[test]
+    fn overrides_take_precedence_over_holidays() {
+        let mut days = vec![
+            day("2020-12-27", WorkingHours::Free, None),
+            day("2020-12-28", WorkingHours::Full, None),
+            day("2020-12-29", WorkingHours::Full, None),
+            day("2020-12-30", WorkingHours::Full, None),
+            day("2020-12-31", WorkingHours::Free, None),
+        ];
+        let date = |date: &str| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok();
+        let overrides = vec![
+            CalendarOverride {
+                date: None,
+                from: date("2020-12-28"),
+                to: date("2020-12-31"),
+                hours: WorkingHours::Free,
+                reason: Some(String::from("Stängt mellan jul och nyår")),
+            },
+            CalendarOverride {
+                date: date("2020-12-31"),
+                from: None,
+                to: None,
+                hours: WorkingHours::Half,
+                reason: None,
+            },
+        ];
+
+        apply_overrides(&mut days, &overrides);
+
+        let hours: Vec<WorkingHours> = days.iter().map(|day| day.hours).collect();
+        assert_eq!(
+            hours,
+            vec![
+                WorkingHours::Free,
+                WorkingHours::Free,
+                WorkingHours::Free,
+                WorkingHours::Free,
+                WorkingHours::Half,
+            ]
+        );
+        assert_eq!(
+            days[1].reason.as_deref(),
+            Some("Stängt mellan jul och nyår")
+        );
+    }
+
+    #[tokio::test]
+    async fn fetched_months_are_cached() {
+        let date = NaiveDate::from_ymd_opt(1900, 1, 1).unwrap();
+        let cached = vec![day("1900-01-01", WorkingHours::Free, Some("Cachad"))];
+        SHOLIDAY_CACHE
+            .lock()
+            .unwrap()
+            .insert((1900, 1), (Instant::now(), cached));
+
+        let days = get_holidays(Country::SE, &date).await.expect("failed");
+
+        assert_eq!(days.len(), 1);
+        assert_eq!(days[0].holiday.as_deref(), Some("Cachad"));
+    }
+
+    #[test]
+    fn offline_month_marks_weekends_and_holidays() {
+        let date = NaiveDate::from_ymd_opt(2020, 5, 1).unwrap();
+        let days = get_offline_month(Country::NO, &date);
+
+        assert_eq!(days.len(), 31);
+        let free: Vec<u32> = days
+            .iter()
+            .filter(|day| !day.is_workday())
+            .map(|day| day.date.day())
+            .collect();
+        // Arbeidernes dag, Grunnlovsdag, Kristi himmelfartsdag and weekends
+        assert_eq!(free, vec![1, 2, 3, 9, 10, 16, 17, 21, 23, 24, 30, 31]);
+        assert_eq!(days[16].holiday.as_deref(), Some("Grunnlovsdag"));
+    }
+
+    #[test]
+    fn sholiday_eves_are_named() {
+        // As sholiday answers for midsommarafton and midsommardagen 2020
+        let response: SholidayFaboulResponse = serde_json::from_str(
+            r#"{
+                "dagar": [
+                    {
+                        "datum": "2020-06-19",
+                        "arbetsfri dag": "Ja",
+                        "helgdagsafton": "Midsommarafton"
+                    },
+                    {
+                        "datum": "2020-06-20",
+                        "arbetsfri dag": "Ja",
+                        "helgdag": "Midsommardagen"
+                    }
+                ]
+            }"#,
+        )
+        .expect("failed");
+
+        let days = get_sholiday_days(&response).expect("failed");
+
+        assert_eq!(days[0].holiday.as_deref(), Some("Midsommarafton"));
+        assert_eq!(days[0].hours, WorkingHours::Free);
+        assert_eq!(days[1].holiday.as_deref(), Some("Midsommardagen"));
+    }
 }
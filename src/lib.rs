@@ -1,5 +1,25 @@
+pub mod admin;
+pub mod api;
 pub mod client;
 pub mod events;
+pub mod format;
+pub mod holidays;
+pub mod ical;
 pub mod last_day;
+pub mod locale;
+pub mod message_context;
+pub mod office_hours;
+pub mod replies;
+pub mod reporting;
+pub mod response_url;
+pub mod shared_config;
+pub mod signature;
+pub mod slash;
+pub mod storage;
+pub mod template;
+pub mod time_off;
+pub mod validation;
+pub mod working_time;
 
 pub mod config;
+pub mod config_source;
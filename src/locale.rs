@@ -0,0 +1,119 @@
+use chrono::Weekday;
+use serde::Deserialize;
+
+/// The language joel-bot answers in, Swedish unless configured otherwise.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    Sv,
+    En,
+}
+
+pub const LOCALES: [Locale; 2] = [Locale::Sv, Locale::En];
+
+const SWEDISH_MONTHS: [&str; 12] = [
+    "januari",
+    "februari",
+    "mars",
+    "april",
+    "maj",
+    "juni",
+    "juli",
+    "augusti",
+    "september",
+    "oktober",
+    "november",
+    "december",
+];
+
+const ENGLISH_MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+impl Locale {
+    /// The code used in config.yaml, e.g. `en`.
+    pub fn get_code(&self) -> &'static str {
+        match self {
+            Locale::Sv => "sv",
+            Locale::En => "en",
+        }
+    }
+
+    pub fn get_month_names(&self) -> &'static [&'static str; 12] {
+        match self {
+            Locale::Sv => &SWEDISH_MONTHS,
+            Locale::En => &ENGLISH_MONTHS,
+        }
+    }
+
+    pub fn get_weekday_name(&self, weekday: &Weekday) -> &'static str {
+        match (self, weekday) {
+            (Locale::Sv, Weekday::Mon) => "måndag",
+            (Locale::Sv, Weekday::Tue) => "tisdag",
+            (Locale::Sv, Weekday::Wed) => "onsdag",
+            (Locale::Sv, Weekday::Thu) => "torsdag",
+            (Locale::Sv, Weekday::Fri) => "fredag",
+            (Locale::Sv, Weekday::Sat) => "lördag",
+            (Locale::Sv, Weekday::Sun) => "söndag",
+            (Locale::En, Weekday::Mon) => "Monday",
+            (Locale::En, Weekday::Tue) => "Tuesday",
+            (Locale::En, Weekday::Wed) => "Wednesday",
+            (Locale::En, Weekday::Thu) => "Thursday",
+            (Locale::En, Weekday::Fri) => "Friday",
+            (Locale::En, Weekday::Sat) => "Saturday",
+            (Locale::En, Weekday::Sun) => "Sunday",
+        }
+    }
+
+    /// Singular and plural of days, hours, minutes and seconds.
+    pub fn get_duration_units(&self) -> [(&'static str, &'static str); 4] {
+        match self {
+            Locale::Sv => [
+                ("dag", "dagar"),
+                ("timme", "timmar"),
+                ("minut", "minuter"),
+                ("sekund", "sekunder"),
+            ],
+            Locale::En => [
+                ("day", "days"),
+                ("hour", "hours"),
+                ("minute", "minutes"),
+                ("second", "seconds"),
+            ],
+        }
+    }
+
+    pub fn get_workday_units(&self) -> (&'static str, &'static str) {
+        match self {
+            Locale::Sv => ("arbetsdag", "arbetsdagar"),
+            Locale::En => ("workday", "workdays"),
+        }
+    }
+
+    /// What joins the last two items of a list, e.g. "2 timmar och 5 minuter".
+    pub fn get_conjunction(&self) -> &'static str {
+        match self {
+            Locale::Sv => " och ",
+            Locale::En => " and ",
+        }
+    }
+
+    pub fn get_decimal_separator(&self) -> char {
+        match self {
+            Locale::Sv => ',',
+            Locale::En => '.',
+        }
+    }
+}
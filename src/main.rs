@@ -1,32 +1,38 @@
 #[macro_use]
 extern crate rocket;
-use crate::last_day::{get_last_workday, is_last_workday};
 
 // Add dotenv support
 use dotenv::dotenv;
-use rand::SeedableRng;
-
-use std::time::Duration;
 
-use chrono::{Datelike, NaiveTime, Utc};
-use chrono_tz::Europe::Stockholm;
+use chrono::{NaiveDate, Utc};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 
-use crate::config::*;
-use rand::rngs::SmallRng;
-use rand::Rng;
-use reqwest::Client;
 use rocket::form::Form;
-use rocket::response::status::Accepted;
+use rocket::http::{ContentType, Status};
 use rocket::serde::json::Json;
 use rocket::State;
 use slack::client::*;
-use slack::events::{SlackRequest, SlackState};
-use std::collections::HashMap;
+use slack::config::*;
+use slack::config_source::ConfigSource;
+use slack::events::{SlackRequest, SlackRetry, SlackState};
+use slack::ical::time_report_calendar;
+use slack::last_day::Calendar;
+use slack::locale::{Locale, LOCALES};
+use slack::message_context::get_contexts;
+use slack::reporting::{get_deadline, ReportingPeriod};
+use slack::shared_config::SharedConfiguration;
+use slack::signature::{SigningSecret, SlackSigned};
+use slack::slash::{
+    Command, CommandRegistry, ResponseType, SlashCommand, SlashContext, SlashResponse,
+};
+use slack::storage::Storage;
+use slack::template::Variables;
+use slack::validation::validate_config;
 use std::sync::Arc;
-use tokio::time::sleep;
 
-mod config;
-mod last_day;
+const DEFAULT_CALENDAR_MONTHS: u32 = 12;
+const MAX_CALENDAR_MONTHS: u32 = 24;
 
 #[rocket::main]
 async fn main() {
@@ -34,14 +40,59 @@ async fn main() {
     dotenv().ok();
 
     let args: Vec<String> = std::env::args().collect();
-    let config = Arc::new(Configuration::read().expect("couldn't read configuration file"));
+
+    if args.contains(&"--operation=validate-config".to_string()) {
+        let diagnostics = validate_config(&ConfigSource::from_args(&args));
+        for diagnostic in &diagnostics {
+            println!("{}", diagnostic);
+        }
+        if !diagnostics.is_empty() {
+            std::process::exit(1);
+        }
+        println!("Configuration is valid");
+        return;
+    }
+
+    if args.contains(&"--operation=preview".to_string()) {
+        let config = ConfigSource::from_args(&args)
+            .load()
+            .expect("couldn't read configuration file");
+        preview(&config, &args).await;
+        return;
+    }
+
+    let config = Arc::new(
+        SharedConfiguration::load(ConfigSource::from_args(&args))
+            .expect("couldn't read configuration file"),
+    );
     let client = Arc::new(SlackClient::new().expect("couldn't initiate slack client"));
 
     if args.contains(&"--operation=api".to_string()) {
-        let slack_events = SlackState::new();
+        let storage = Arc::new(Storage::open().expect("couldn't open storage"));
+        tokio::spawn(config.clone().watch());
+        let slack_events = SlackState::new(client.clone(), storage.clone(), config.clone());
+        let signing_secret = SigningSecret::from_env();
+        if !signing_secret.is_configured() {
+            println!("JOEL_BOT_SLACK_SIGNING_SECRET isn't set, so events aren't verified and admin commands are refused");
+        }
+        let slash_context = SlashContext {
+            config: config.clone(),
+            client: client.clone(),
+            storage: storage.clone(),
+        };
         rocket::build()
             .manage(slack_events)
-            .mount("/", routes![slack_request, time_report, gg])
+            .manage(signing_secret)
+            .manage(config.clone())
+            .manage(client.clone())
+            .manage(storage)
+            .manage(slash_context)
+            .manage(CommandRegistry::default())
+            .mount(
+                "/",
+                routes![slack_request, slash, time_report, gg, time_report_ics],
+            )
+            .mount("/api/v1", slack::api::routes())
             .launch()
             .await
             .expect("Server failed to start");
@@ -49,212 +100,353 @@ async fn main() {
     }
 
     if args.contains(&"--operation=check_last_workday".to_string()) {
-        last_workday_message(config.clone(), client.clone()).await;
+        let storage = Storage::open().expect("couldn't open storage");
+        last_workday_message(config.get(), client.clone(), &storage).await;
         return;
     }
 
-    eprintln!("Please specify an --operation: api or check_last_workday");
-    println!("Usage: joel-bot --operation=<operation>");
+    eprintln!("Please specify an --operation: api, check_last_workday, preview or validate-config");
+    println!("Usage: joel-bot --operation=<operation> [--config=<path>]");
     println!("Operations:");
     println!("  api                   Start the Slack API server");
-    println!("  check_last_workday    Check if today is the last workday of the month and send a message to Slack if so");
+    println!("  check_last_workday    Check if today is the last workday of the month and send a message to Slack if so,");
+    println!("                        remind the channels of clients whose reporting deadline is today and announce pay day");
+    println!("  preview               Print what check_last_workday would post on --date=YYYY-MM-DD without");
+    println!("                        contacting Slack. --seed=<number> picks the same messages every time and");
+    println!("                        --all prints every reminder that can be put together for the date instead and");
+    println!("                        --channel=<name>:<id> previews the client channel with that name in the locale");
+    println!("                        and country of the Slack channel with that id");
+    println!("  validate-config       Check the configuration and print where any problems are");
+    println!("Configuration:");
+    println!("  --config=<path>       Configuration file, defaults to JOEL_BOT_CONFIG or config.yaml. A file for the");
+    println!("                        environment next to it, e.g. config.prod.yaml for APP_ENVIRONMENT=prod, is read on top");
+    println!("  JOEL_BOT__<KEY>__<KEY> Overrides a single key, e.g. JOEL_BOT__PAYDAY__CHANNEL=lön");
 }
 
-async fn last_workday_message(config: Arc<Configuration>, client: Arc<SlackClient>) {
+async fn last_workday_message(
+    config: Arc<Configuration>,
+    client: Arc<SlackClient>,
+    storage: &Storage,
+) {
     let today = Utc::now().date_naive();
-    match is_last_workday(&today).await {
+    match config.get_calendar().is_last_workday(&today).await {
         Ok(true) => {
-            let context = today.month().to_string();
-            let message = config.get_message(&context);
-            match client.get_channel_id_by_name("allmant").await {
-                Some(channel_id) => {
-                    if let Err(error) = client.post_message(&channel_id, &message).await {
-                        println!("couldn't post message: {}", error)
-                    }
-                }
-                None => println!("no channel with name 'allmant' found!"),
-            }
+            let locale = get_channel_locale(&config, &client, "allmant").await;
+            let calendar = config.get_calendar();
+            let contexts = get_contexts(&calendar, &ReportingPeriod::Monthly, &today).await;
+            let variables = get_variables(&config, &calendar, &today, &today, locale).await;
+            let message = config
+                .get_message(
+                    locale,
+                    &ReportingPeriod::Monthly,
+                    &contexts,
+                    &variables,
+                    storage,
+                )
+                .await;
+            post_to_channel(&client, "allmant", &message).await;
         }
         Ok(false) => println!("Not last work day"),
         Err(_) => {
             // TODO Maybe handle error or nah?
         }
     };
+
+    client_reminders(&config, &client, storage, &today).await;
+    payday_message(&config, &client, &today).await;
 }
 
-#[post("/slack-request", format = "application/json", data = "<request>")]
-async fn slack_request(state: &State<SlackState>, request: Json<SlackRequest>) -> String {
-    let slack_request_data = request.into_inner();
-    state.handle_request(slack_request_data).await
+async fn payday_message(config: &Configuration, client: &SlackClient, today: &NaiveDate) {
+    let payday = config.get_payday();
+    let (channel, message) = match (&payday.channel, payday.get_message()) {
+        (Some(channel), Some(message)) => (channel, message),
+        _ => return,
+    };
+
+    match get_deadline(&config.get_calendar(), &payday.get_period(), today).await {
+        Ok(date) if date == *today => post_to_channel(client, channel, &message).await,
+        Ok(_) => println!("Not pay day"),
+        Err(error) => println!("failed to get pay day: {}", error),
+    }
 }
 
-// More information here: https://api.slack.com/interactivity/slash-commands
-#[derive(FromForm)]
-struct SlackSlashMessage {
-    // token: String, <-- We should save and validate this
-    // command: String, <-- can be used to check what command was used.
-    // text: Option<String>,
-    response_url: String,
+// Clients with their own reporting periods are reminded in their own channels
+async fn client_reminders(
+    config: &Configuration,
+    client: &SlackClient,
+    storage: &Storage,
+    today: &NaiveDate,
+) {
+    for (name, reporting_client) in config.get_clients() {
+        let channel = match &reporting_client.channel {
+            Some(channel) => channel,
+            None => continue,
+        };
+        // Countries and locales are set per channel id, for clients abroad
+        let channel_id = client.get_channel_id_by_name(channel).await;
+        let calendar = config.get_calendar_for(None, channel_id.as_deref());
+        let period = &reporting_client.period;
+
+        match get_deadline(&calendar, period, today).await {
+            Ok(deadline) if deadline == *today => {
+                let locale = config.get_locale_for(None, channel_id.as_deref());
+                let contexts = get_contexts(&calendar, period, today).await;
+                let variables = get_variables(config, &calendar, &deadline, today, locale).await;
+                let message = config
+                    .get_message(locale, period, &contexts, &variables, storage)
+                    .await;
+                post_to_channel(client, channel, &message).await;
+            }
+            Ok(_) => println!("Not time to report for {}", name),
+            Err(error) => println!("failed to get deadline for {}: {}", name, error),
+        }
+    }
 }
 
-#[post(
-    "/time-report",
-    format = "application/x-www-form-urlencoded",
-    data = "<request>"
-)]
-async fn time_report(request: Form<SlackSlashMessage>) -> Accepted<String> {
-    let response_url = request.response_url.clone();
-
-    let calculations = [
-        "vänta",
-        "beräknar",
-        "processerar",
-        "finurlar",
-        "gnuggar halvledarna",
-        "tömmer kvicksilver-depå",
-        "springer i cirklar",
-        "kryssar och jämför",
-        "skruvar och muttrar",
-        "går på djupet",
-    ];
-
-    tokio::spawn(async move {
-        let today = Utc::now().naive_utc().date();
-        let http_client = Client::new();
-        let mut map = HashMap::new();
-        let mut rng = SmallRng::from_os_rng();
-
-        match get_last_workday(&today).await {
-            Ok(last_workday) => {
-                if last_workday == today {
-                    map.insert("text", format!("Okej, jag har kikat i kalendern och det är först *{}* som du behöver tidrapportera!", last_workday));
-
-                    sleep_and_send_time_report_response(&http_client, &response_url, &map).await;
-
-                    for _ in 0..2 {
-                        let pos = rng.random_range(0..calculations.len());
-
-                        map.insert("text", format!("... {}", calculations[pos]));
-
-                        sleep_and_send_time_report_response(&http_client, &response_url, &map)
-                            .await;
-                    }
-
-                    map.insert("text", String::from("... det är ju idag!"));
-
-                    sleep_and_send_time_report_response(&http_client, &response_url, &map).await;
-                } else {
-                    map.insert("text", format!("Nu har jag gjort diverse uppslag och scrape:at nätet och det är inte förrän *{}* som du behöver tidrapportera!", last_workday));
+// What check_last_workday would post, printed instead. Channel locales and countries are set per
+// channel id, which takes Slack to look up, so `--channel=<name>:<id>` gives the id of a client
+// channel and everything else is previewed with the defaults
+async fn preview(config: &Configuration, args: &[String]) {
+    let date = match get_argument(args, "--date=")
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+    {
+        Some(date) => date,
+        None => {
+            eprintln!("Please specify the --date=YYYY-MM-DD to preview");
+            std::process::exit(1);
+        }
+    };
+    let mut rng = match get_argument(args, "--seed=").map(str::parse::<u64>) {
+        Some(Ok(seed)) => StdRng::seed_from_u64(seed),
+        Some(Err(_)) => {
+            eprintln!("--seed must be a number");
+            std::process::exit(1);
+        }
+        None => StdRng::from_os_rng(),
+    };
+    let all = args.contains(&"--all".to_string());
+    // Only read, so the history of picked messages is left as it is
+    let storage = Storage::open().expect("couldn't open storage");
+    let channel_ids = match get_argument(args, "--channel=").map(|channel| channel.split_once(':'))
+    {
+        Some(Some((name, id))) => Some((name, id)),
+        Some(None) => {
+            eprintln!(
+                "--channel must be a channel name and id, e.g. --channel=kund-volvo:C0123456789"
+            );
+            std::process::exit(1);
+        }
+        None => None,
+    };
+    let calendar = config.get_calendar();
+
+    let preview_reminder = async |calendar: &Calendar,
+                                  locale: Locale,
+                                  period: &ReportingPeriod,
+                                  deadline: &NaiveDate,
+                                  rng: &mut StdRng| {
+        let contexts = get_contexts(calendar, period, &date).await;
+        let variables = get_variables(config, calendar, deadline, &date, locale).await;
+        if all {
+            config
+                .preview_all_messages(locale, period, &contexts, &variables, &storage)
+                .await
+        } else {
+            vec![
+                config
+                    .preview_message(locale, period, &contexts, &variables, &storage, rng)
+                    .await,
+            ]
+        }
+    };
 
-                    sleep_and_send_time_report_response(&http_client, &response_url, &map).await
-                }
-            }
-            Err(error) => {
-                println!("failed to get last work day: {}", error);
+    match calendar.is_last_workday(&date).await {
+        Ok(true) => print_preview(
+            "allmant",
+            &preview_reminder(
+                &calendar,
+                config.get_locale_for(None, None),
+                &ReportingPeriod::Monthly,
+                &date,
+                &mut rng,
+            )
+            .await,
+        ),
+        Ok(false) => println!("Not last work day"),
+        Err(error) => println!("failed to check the last work day: {}", error),
+    }
 
-                map.insert("text", String::from("Misslyckades stenhårt..."));
-                sleep_and_send_time_report_response(&http_client, &response_url, &map).await;
-            }
+    // Sorted so a seed picks the same messages every time
+    let mut clients: Vec<_> = config.get_clients().iter().collect();
+    clients.sort_by_key(|(name, _)| name.as_str());
+    for (name, reporting_client) in clients {
+        let channel = match &reporting_client.channel {
+            Some(channel) => channel,
+            None => continue,
         };
-    });
+        let channel_id = channel_ids
+            .filter(|(name, _)| name == channel)
+            .map(|(_, id)| id);
+        let client_calendar = config.get_calendar_for(None, channel_id);
+        let locale = config.get_locale_for(None, channel_id);
+        let period = &reporting_client.period;
+
+        match get_deadline(&client_calendar, period, &date).await {
+            Ok(deadline) if deadline == date => print_preview(
+                channel,
+                &preview_reminder(&client_calendar, locale, period, &deadline, &mut rng).await,
+            ),
+            Ok(_) => println!("Not time to report for {}", name),
+            Err(error) => println!("failed to get deadline for {}: {}", name, error),
+        }
+    }
 
-    Accepted("Ska ta en titt i kalendern...".to_string())
+    let payday = config.get_payday();
+    if let Some(channel) = &payday.channel {
+        match get_deadline(&calendar, &payday.get_period(), &date).await {
+            Ok(payday_date) if payday_date == date => {
+                let messages = if all {
+                    payday.get_messages().to_vec()
+                } else {
+                    payday.get_message_with(&mut rng).into_iter().collect()
+                };
+                print_preview(channel, &messages);
+            }
+            Ok(_) => println!("Not pay day"),
+            Err(error) => println!("failed to get pay day: {}", error),
+        }
+    }
 }
 
-async fn sleep_and_send_time_report_response(
-    http_client: &Client,
-    url: &String,
-    map: &HashMap<&str, String>,
-) {
-    // To "fool" the user that we are actually calculating something
-    sleep(Duration::from_secs(2)).await;
-
-    let resp = http_client.post(url.as_str()).json(map).send();
-
-    match resp.await {
-        Ok(r) => {
-            if !r.status().is_success() {
-                println!("failed to send message, {}", r.status().as_str());
-                let result = r.text().await;
-                if result.is_ok() {
-                    println!("{}", result.unwrap());
-                }
+fn print_preview(channel: &str, messages: &[String]) {
+    for message in messages {
+        println!("\n#{}:\n{}", channel, message);
+    }
+}
+
+fn get_argument<'a>(args: &'a [String], prefix: &str) -> Option<&'a str> {
+    args.iter().find_map(|arg| arg.strip_prefix(prefix))
+}
+
+async fn get_variables(
+    config: &Configuration,
+    calendar: &Calendar,
+    deadline: &NaiveDate,
+    today: &NaiveDate,
+    locale: Locale,
+) -> Variables {
+    let deadline_time = config.get_working_time().office_hours.end;
+    Variables::for_deadline(calendar, deadline, &deadline_time, today, locale)
+        .await
+        .unwrap_or_else(|error| {
+            println!("failed to get message variables: {}", error);
+            Variables::default()
+        })
+}
+
+// Locales are set per channel id, like countries
+async fn get_channel_locale(config: &Configuration, client: &SlackClient, channel: &str) -> Locale {
+    let channel_id = client.get_channel_id_by_name(channel).await;
+    config.get_locale_for(None, channel_id.as_deref())
+}
+
+async fn post_to_channel(client: &SlackClient, channel: &str, message: &str) {
+    match client.get_channel_id_by_name(channel).await {
+        Some(channel_id) => {
+            if let Err(error) = client.post_message(&channel_id, message).await {
+                println!("couldn't post message: {}", error)
             }
         }
-        Err(err) => {
-            println!("got exception while sending message: {}", err)
+        None => println!("no channel with name '{}' found!", channel),
+    }
+}
+
+#[post("/slack-request", format = "application/json", data = "<request>")]
+async fn slack_request(
+    state: &State<SlackState>,
+    retry: SlackRetry,
+    request: SlackSigned<SlackRequest>,
+) -> String {
+    state
+        .handle_request(request.value, retry, request.verified)
+        .await
+}
+
+// Subscribable in Outlook/Google Calendar, e.g. `/calendar/time-report.ics?months=6&locale=en`
+#[get("/calendar/time-report.ics?<months>&<locale>")]
+async fn time_report_ics(
+    config: &State<Arc<SharedConfiguration>>,
+    months: Option<u32>,
+    locale: Option<&str>,
+) -> Result<(ContentType, String), Status> {
+    let months = months
+        .unwrap_or(DEFAULT_CALENDAR_MONTHS)
+        .clamp(1, MAX_CALENDAR_MONTHS);
+    let now = Utc::now();
+    let config = config.get();
+    // Unknown locales get the default one, like users and channels without one
+    let locale = LOCALES
+        .into_iter()
+        .find(|known| Some(known.get_code()) == locale)
+        .unwrap_or_else(|| config.get_locale_for(None, None));
+
+    match config
+        .get_calendar()
+        .get_last_workdays(&now.date_naive(), months)
+        .await
+    {
+        Ok(deadlines) => Ok((
+            ContentType::Calendar,
+            time_report_calendar(&deadlines, &now, &config.get_replies(locale)),
+        )),
+        Err(error) => {
+            println!("failed to get last work days: {}", error);
+            Err(Status::ServiceUnavailable)
         }
     }
 }
 
 #[post(
-    "/gg",
+    "/slash",
     format = "application/x-www-form-urlencoded",
     data = "<request>"
 )]
-async fn gg(request: Form<SlackSlashMessage>) -> Accepted<String> {
-    // 'request' is unused, so prefix with underscore to silence warning
-    let _request = request;
-    let upper = NaiveTime::from_hms_opt(17, 0, 0).unwrap();
-    let lower = NaiveTime::from_hms_opt(8, 0, 0).unwrap();
-
-    let date = Utc::now();
-    let time = date.with_timezone(&Stockholm).time();
-
-    let message = if time < upper && time >= lower {
-        let delta = upper - time;
-        let string = generate_formatted_duration(&delta);
-        format!("Nu är det bara {} innan du kan packa ihop för dagen, tänk vad kul du kan ha i {} till! :smiley:", string, string)
-    } else if time < lower {
-        let delta = lower - time;
-        let string = generate_formatted_duration(&delta);
-        format!("Var lugn! Du behöver inte börja jobba förrän om {}", string)
-    } else {
-        format!(
-            "Klockan är efter {}, stay calm och sluta jobba!",
-            upper.format("%H:%M")
-        )
-    };
-
-    Accepted(message)
+async fn slash(
+    registry: &State<CommandRegistry>,
+    context: &State<SlashContext>,
+    request: Form<SlashCommand>,
+) -> Json<SlashResponse> {
+    Json(registry.dispatch(context, request.into_inner()).await)
 }
 
-fn generate_formatted_duration(duration: &chrono::Duration) -> String {
-    let mut formatted = String::new();
-    let seconds = duration.num_seconds() % 60;
-    let minutes = duration.num_minutes() % 60;
-    let hours = duration.num_hours();
-
-    let append = |s1: &str, s2: &str| -> String {
-        return if s1.len() > 0 {
-            format!("{} och {}", s1, s2)
-        } else {
-            String::from(s2)
-        };
-    };
-
-    match minutes {
-        1 => formatted = append(&formatted, format!("{} minut", minutes).as_str()),
-        minutes if minutes > 1 => {
-            formatted = append(&formatted, format!("{} minuter", minutes).as_str())
-        }
-        _ => {}
-    };
-
-    match hours {
-        1 => formatted = append(&format!("{} timme", hours), formatted.as_str()),
-        hours if hours > 1 => formatted = append(&format!("{} timmar", hours), formatted.as_str()),
-        _ => {
-            // Add seconds only when hours are < 1
-            match seconds {
-                1 => formatted = append(&formatted, format!("{} sekund", seconds).as_str()),
-                seconds if seconds > 1 => {
-                    formatted = append(&formatted, format!("{} sekunder", seconds).as_str())
-                }
-                _ => {}
-            };
-        }
-    };
+// Kept for the slash commands that were set up in Slack before `/slash`
+#[post(
+    "/time-report",
+    format = "application/x-www-form-urlencoded",
+    data = "<request>"
+)]
+async fn time_report(
+    context: &State<SlashContext>,
+    request: Form<SlashCommand>,
+) -> Json<SlashResponse> {
+    let response = context
+        .run(
+            Command::TimeReport,
+            ResponseType::Ephemeral,
+            request.into_inner(),
+        )
+        .await;
+    Json(response)
+}
 
-    formatted
+#[post(
+    "/gg",
+    format = "application/x-www-form-urlencoded",
+    data = "<request>"
+)]
+async fn gg(context: &State<SlashContext>, request: Form<SlashCommand>) -> Json<SlashResponse> {
+    let response = context
+        .run(Command::Gg, ResponseType::Ephemeral, request.into_inner())
+        .await;
+    Json(response)
 }
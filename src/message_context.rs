@@ -0,0 +1,247 @@
+use anyhow::Result;
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+
+use crate::holidays::{first_weekday_from, get_easter_sunday};
+use crate::last_day::Calendar;
+use crate::reporting::{get_deadline, ReportingPeriod};
+
+// Longer than any reporting period, so there is always a reminder between this far before a day
+// and the day
+const REMINDER_SEARCH_DAYS: u64 = 45;
+
+// Most are back from their summer vacation in August
+const FIRST_MONTH_AFTER_SUMMER: u32 = 8;
+
+/// Context keys for messages that are picked by what is going on, in order of precedence.
+pub const CONTEXTS: [&str; 7] = [
+    "påsk",
+    "midsommar",
+    "årsslut",
+    "jul",
+    "kvartalsslut",
+    "efter_sommaren",
+    "fredag",
+];
+
+/// The message context keys for the `period` reminder on `date`, most specific first: the events
+/// going on in [`CONTEXTS`] order, then the month number and finally `general`. Holidays are
+/// celebrated in the reminder nearest to them and `årsslut` in the last reminder of the year.
+pub async fn get_contexts(
+    calendar: &Calendar,
+    period: &ReportingPeriod,
+    date: &NaiveDate,
+) -> Vec<String> {
+    let mut contexts = Vec::new();
+    for context in CONTEXTS {
+        match is_context(context, calendar, period, date).await {
+            Ok(true) => contexts.push(context.to_string()),
+            Ok(false) => {}
+            Err(error) => println!("failed to check the {} context: {}", context, error),
+        }
+    }
+
+    contexts.push(date.month().to_string());
+    contexts.push(String::from("general"));
+    contexts
+}
+
+/// Whether `key` can be used as a context in `time_report`.
+pub fn is_known_context(key: &str) -> bool {
+    key == "general"
+        || CONTEXTS.contains(&key)
+        || key
+            .parse::<u32>()
+            .is_ok_and(|month| (1..=12).contains(&month))
+}
+
+async fn is_context(
+    context: &str,
+    calendar: &Calendar,
+    period: &ReportingPeriod,
+    date: &NaiveDate,
+) -> Result<bool> {
+    let midsommarafton = || {
+        first_weekday_from(
+            NaiveDate::from_ymd_opt(date.year(), 6, 19).unwrap(),
+            Weekday::Fri,
+        )
+    };
+
+    Ok(match context {
+        "påsk" => {
+            is_nearest_reminder(calendar, period, date, &get_easter_sunday(date.year())).await?
+        }
+        "midsommar" => is_nearest_reminder(calendar, period, date, &midsommarafton()).await?,
+        "årsslut" => get_next_reminder(calendar, period, date).await?.year() != date.year(),
+        "jul" => date.month() == 12,
+        "kvartalsslut" => {
+            let next = get_next_reminder(calendar, period, date).await?;
+            get_quarter(&next) != get_quarter(date)
+        }
+        "efter_sommaren" => {
+            let summer_end =
+                NaiveDate::from_ymd_opt(date.year(), FIRST_MONTH_AFTER_SUMMER, 1).unwrap();
+            *date >= summer_end
+                && get_previous_reminder(calendar, period, date)
+                    .await?
+                    .is_none_or(|previous| previous < summer_end)
+        }
+        "fredag" => date.weekday() == Weekday::Fri,
+        _ => false,
+    })
+}
+
+// Whether the reminder on `date` is the one nearest to `holiday`, which is either the last one on
+// or before it or the first one after it
+async fn is_nearest_reminder(
+    calendar: &Calendar,
+    period: &ReportingPeriod,
+    date: &NaiveDate,
+    holiday: &NaiveDate,
+) -> Result<bool> {
+    if (*date - *holiday).num_days().unsigned_abs() > REMINDER_SEARCH_DAYS {
+        return Ok(false);
+    }
+
+    let day_after = holiday.succ_opt().unwrap();
+    let before = get_previous_reminder(calendar, period, &day_after).await?;
+    let after = get_deadline(calendar, period, &day_after).await?;
+
+    let nearest = match before {
+        Some(before) if *holiday - before <= after - *holiday => before,
+        _ => after,
+    };
+    Ok(nearest == *date)
+}
+
+async fn get_next_reminder(
+    calendar: &Calendar,
+    period: &ReportingPeriod,
+    date: &NaiveDate,
+) -> Result<NaiveDate> {
+    get_deadline(calendar, period, &date.succ_opt().unwrap()).await
+}
+
+// The last reminder before `date`, if there is one within the search window
+async fn get_previous_reminder(
+    calendar: &Calendar,
+    period: &ReportingPeriod,
+    date: &NaiveDate,
+) -> Result<Option<NaiveDate>> {
+    let mut previous = None;
+    let mut from = *date - Days::new(REMINDER_SEARCH_DAYS);
+    loop {
+        let deadline = get_deadline(calendar, period, &from).await?;
+        if deadline >= *date {
+            return Ok(previous);
+        }
+        previous = Some(deadline);
+        from = deadline.succ_opt().unwrap();
+    }
+}
+
+fn get_quarter(date: &NaiveDate) -> (i32, u32) {
+    (date.year(), date.month0() / 3)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{NaiveDate, Weekday};
+
+    use super::{get_contexts, is_known_context};
+    use crate::holidays::Country;
+    use crate::last_day::Calendar;
+    use crate::reporting::ReportingPeriod;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    async fn monthly(date: NaiveDate) -> Vec<String> {
+        let calendar = Calendar::new(Default::default(), Country::NO);
+        get_contexts(&calendar, &ReportingPeriod::Monthly, &date).await
+    }
+
+    #[tokio::test]
+    async fn easter_is_in_the_nearest_reminder() {
+        // Easter was on the 12th of April 2020, 12 days after the March reminder
+        assert_eq!(
+            monthly(date(2020, 3, 31)).await,
+            vec!["påsk", "kvartalsslut", "3", "general"]
+        );
+        assert_eq!(monthly(date(2020, 4, 30)).await, vec!["4", "general"]);
+        // and on the 31st of March 2024, after the last workday in March
+        assert_eq!(monthly(date(2024, 2, 29)).await, vec!["2", "general"]);
+        assert_eq!(
+            monthly(date(2024, 3, 27)).await,
+            vec!["påsk", "kvartalsslut", "3", "general"]
+        );
+        // The 13th of April 2031 is 13 days after the March reminder and 17 before the April one
+        assert_eq!(
+            monthly(date(2031, 3, 31)).await,
+            vec!["påsk", "kvartalsslut", "3", "general"]
+        );
+        assert_eq!(monthly(date(2031, 4, 30)).await, vec!["4", "general"]);
+    }
+
+    #[tokio::test]
+    async fn special_dates() {
+        assert_eq!(
+            monthly(date(2020, 6, 30)).await,
+            vec!["midsommar", "kvartalsslut", "6", "general"]
+        );
+        assert_eq!(
+            monthly(date(2020, 12, 31)).await,
+            vec!["årsslut", "jul", "kvartalsslut", "12", "general"]
+        );
+        assert_eq!(
+            monthly(date(2020, 8, 31)).await,
+            vec!["efter_sommaren", "8", "general"]
+        );
+    }
+
+    #[tokio::test]
+    async fn the_year_ends_with_its_last_reminder() {
+        let calendar = Calendar::new(Default::default(), Country::NO);
+        let weekly = ReportingPeriod::Weekly {
+            weekday: Weekday::Fri,
+        };
+
+        assert_eq!(
+            get_contexts(&calendar, &weekly, &date(2020, 12, 18)).await,
+            vec!["jul", "fredag", "12", "general"]
+        );
+        // The 1st of January 2021 was a Friday, so that week is reported on New Year's Eve
+        assert_eq!(
+            get_contexts(&calendar, &weekly, &date(2020, 12, 31)).await,
+            vec!["årsslut", "jul", "kvartalsslut", "12", "general"]
+        );
+        // The quarter ends with the last reminder before the next one in April
+        assert_eq!(
+            get_contexts(&calendar, &weekly, &date(2020, 3, 20)).await,
+            vec!["fredag", "3", "general"]
+        );
+        assert_eq!(
+            get_contexts(&calendar, &weekly, &date(2020, 3, 27)).await,
+            vec!["kvartalsslut", "fredag", "3", "general"]
+        );
+        // Summer is over in the first reminder in August
+        assert_eq!(
+            get_contexts(&calendar, &weekly, &date(2020, 8, 7)).await,
+            vec!["efter_sommaren", "fredag", "8", "general"]
+        );
+        assert_eq!(
+            get_contexts(&calendar, &weekly, &date(2020, 8, 14)).await,
+            vec!["fredag", "8", "general"]
+        );
+    }
+
+    #[test]
+    fn known_contexts() {
+        assert!(is_known_context("general"));
+        assert!(is_known_context("12"));
+        assert!(is_known_context("påsk"));
+        assert!(!is_known_context("13"));
+        assert!(!is_known_context("pask"));
+    }
+}
@@ -0,0 +1,311 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+use crate::format::{format_date, generate_formatted_duration, weekday_name};
+use crate::last_day::{Calendar, CalendarDay, WorkingHours};
+use crate::replies::{Replies, Reply};
+use crate::template::Variables;
+
+/// When a workday starts and ends, as used by `/gg`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct OfficeHours {
+    #[serde(with = "time_format")]
+    pub start: NaiveTime,
+    #[serde(with = "time_format")]
+    pub end: NaiveTime,
+    #[serde(default)]
+    pub lunch: Option<Lunch>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Lunch {
+    #[serde(with = "time_format")]
+    pub start: NaiveTime,
+    #[serde(with = "time_format")]
+    pub end: NaiveTime,
+}
+
+impl Default for OfficeHours {
+    fn default() -> Self {
+        OfficeHours {
+            start: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+            lunch: Some(Lunch {
+                start: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
+                end: NaiveTime::from_hms_opt(13, 0, 0).unwrap(),
+            }),
+        }
+    }
+}
+
+impl OfficeHours {
+    /// Half days skip lunch and end after half of the day's working time.
+    pub fn to_half_day(&self) -> OfficeHours {
+        let working_time = self.get_working_time_left(&self.start);
+        OfficeHours {
+            start: self.start,
+            end: self.start + working_time / 2,
+            lunch: None,
+        }
+    }
+
+    /// Working time from `time` until the end of the day, not counting lunch.
+    pub fn get_working_time_left(&self, time: &NaiveTime) -> Duration {
+        let mut left = self.end - *time;
+        if let Some(lunch) = &self.lunch {
+            let lunch_left = lunch.end.min(self.end) - lunch.start.max(*time);
+            if lunch_left > Duration::zero() {
+                left -= lunch_left;
+            }
+        }
+        left
+    }
+
+    pub fn is_lunch(&self, time: &NaiveTime) -> bool {
+        self.lunch
+            .as_ref()
+            .is_some_and(|lunch| lunch.start <= *time && *time < lunch.end)
+    }
+
+    /// The `/gg` answer on a weekend, holiday or company day off.
+    pub fn get_day_off_message(
+        &self,
+        now: &NaiveDateTime,
+        today: &CalendarDay,
+        next_workday: &NaiveDate,
+        replies: &Replies,
+    ) -> String {
+        let day = match (&today.holiday, &today.reason) {
+            (Some(holiday), _) => holiday.clone(),
+            (None, Some(reason)) => replies.get(
+                Reply::GgDayOffReason,
+                &Variables::default().with("reason", reason.to_lowercase()),
+            ),
+            (None, None) => weekday_name(&now.weekday(), replies.get_locale()).to_string(),
+        };
+        let variables = Variables::default().with("day", day).with(
+            "next_workday",
+            self.get_next_workday_message(now, next_workday, replies),
+        );
+
+        replies.get(Reply::GgDayOff, &variables)
+    }
+
+    pub fn get_next_workday_message(
+        &self,
+        now: &NaiveDateTime,
+        next_workday: &NaiveDate,
+        replies: &Replies,
+    ) -> String {
+        let locale = replies.get_locale();
+        let start = next_workday.and_time(self.start);
+        let variables = Variables::default()
+            .with("date", format_date(next_workday, locale))
+            .with("time", self.start.format("%H:%M").to_string())
+            .with(
+                "duration",
+                generate_formatted_duration(&(start - *now), locale),
+            );
+
+        replies.get(Reply::GgNextWorkday, &variables)
+    }
+
+    /// The `/gg` answer for someone with these office hours at `time` on a workday.
+    pub fn get_gg_message(&self, time: &NaiveTime, replies: &Replies) -> String {
+        let duration = |duration| generate_formatted_duration(&duration, replies.get_locale());
+
+        if *time < self.start {
+            let variables = Variables::default().with("duration", duration(self.start - *time));
+            replies.get(Reply::GgBeforeStart, &variables)
+        } else if self.is_lunch(time) {
+            let variables =
+                Variables::default().with("duration", duration(self.get_working_time_left(time)));
+            replies.get(Reply::GgLunch, &variables)
+        } else if *time < self.end {
+            let variables =
+                Variables::default().with("duration", duration(self.get_working_time_left(time)));
+            replies.get(Reply::GgWorking, &variables)
+        } else {
+            let variables = Variables::default().with("end", self.end.format("%H:%M").to_string());
+            replies.get(Reply::GgAfterEnd, &variables)
+        }
+    }
+}
+
+/// The `/gg` answer at `now`, taking weekends, holidays and half days into account.
+pub async fn get_gg_answer(
+    office_hours: &OfficeHours,
+    calendar: &Calendar,
+    now: &NaiveDateTime,
+    replies: &Replies<'_>,
+) -> String {
+    let today = calendar.get_day(&now.date()).await;
+    let next_workday = calendar.get_next_workday(&now.date()).await;
+
+    match (today, next_workday) {
+        (Ok(today), Ok(next_workday)) if !today.is_workday() => {
+            office_hours.get_day_off_message(now, &today, &next_workday, replies)
+        }
+        (Ok(today), Ok(next_workday)) => {
+            let office_hours = if today.hours == WorkingHours::Half {
+                office_hours.to_half_day()
+            } else {
+                office_hours.clone()
+            };
+            let message = office_hours.get_gg_message(&now.time(), replies);
+            if now.time() >= office_hours.end {
+                format!(
+                    "{} {}",
+                    message,
+                    office_hours.get_next_workday_message(now, &next_workday, replies)
+                )
+            } else {
+                message
+            }
+        }
+        (Err(error), _) | (_, Err(error)) => {
+            println!("failed to get today from the calendar: {}", error);
+            office_hours.get_gg_message(&now.time(), replies)
+        }
+    }
+}
+
+/// Parses a range like `07:30-16:00`.
+pub fn parse_time_range(range: &str) -> Option<(NaiveTime, NaiveTime)> {
+    let (start, end) = range.split_once('-')?;
+    let start = NaiveTime::parse_from_str(start.trim(), time_format::FORMAT).ok()?;
+    let end = NaiveTime::parse_from_str(end.trim(), time_format::FORMAT).ok()?;
+
+    if start < end {
+        Some((start, end))
+    } else {
+        None
+    }
+}
+
+// Times are written as HH:MM in config.yaml and storage
+mod time_format {
+    use chrono::NaiveTime;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub const FORMAT: &str = "%H:%M";
+
+    pub fn serialize<S: Serializer>(time: &NaiveTime, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&time.format(FORMAT).to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveTime, D::Error> {
+        let time = String::deserialize(deserializer)?;
+        NaiveTime::parse_from_str(&time, FORMAT).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, NaiveDate, NaiveTime};
+
+    use super::{get_gg_answer, parse_time_range, OfficeHours};
+    use std::path::Path;
+
+    use crate::config::Configuration;
+    use crate::holidays::Country;
+    use crate::last_day::Calendar;
+    use crate::locale::Locale;
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn lunch_is_not_working_time() {
+        let hours = OfficeHours::default();
+
+        assert_eq!(hours.get_working_time_left(&time(8, 0)), Duration::hours(8));
+        assert_eq!(
+            hours.get_working_time_left(&time(12, 30)),
+            Duration::hours(4)
+        );
+        assert_eq!(
+            hours.get_working_time_left(&time(14, 15)),
+            Duration::minutes(165)
+        );
+        assert!(hours.is_lunch(&time(12, 0)));
+        assert!(!hours.is_lunch(&time(13, 0)));
+    }
+
+    #[test]
+    fn half_days_end_before_lunch() {
+        let half_day = OfficeHours::default().to_half_day();
+
+        assert_eq!(half_day.end, time(12, 0));
+        assert_eq!(half_day.lunch, None);
+    }
+
+    #[test]
+    fn time_ranges() {
+        assert_eq!(
+            parse_time_range("07:30-16:00"),
+            Some((time(7, 30), time(16, 0)))
+        );
+        assert_eq!(parse_time_range("16:00-07:30"), None);
+        assert_eq!(parse_time_range("7.30-16"), None);
+    }
+
+    #[test]
+    fn office_hours_are_read_as_hours_and_minutes() {
+        let hours: OfficeHours = serde_yaml::from_str(
+            "start: \"07:30\"\nend: \"16:00\"\nlunch:\n  start: \"11:30\"\n  end: \"12:00\"",
+        )
+        .expect("failed to parse");
+
+        assert_eq!(hours.start, time(7, 30));
+        assert_eq!(hours.lunch.map(|lunch| lunch.end), Some(time(12, 0)));
+    }
+
+    #[tokio::test]
+    async fn weekends_count_down_to_next_workday() {
+        let calendar = Calendar::new(Default::default(), Country::FI);
+        let saturday = NaiveDate::from_ymd_opt(2020, 10, 31)
+            .unwrap()
+            .and_time(time(10, 0));
+
+        let config = Configuration::load(Path::new("config.yaml")).expect("failed");
+        let replies = config.get_replies(Locale::Sv);
+
+        let answer = get_gg_answer(&OfficeHours::default(), &calendar, &saturday, &replies).await;
+
+        assert_eq!(answer, "Det är Pyhäinpäivä, stäng datorn! :palm_tree: Nästa arbetsdag börjar måndag 2 november kl 08:00, om 1 dag och 22 timmar.");
+    }
+
+    #[tokio::test]
+    async fn evenings_count_down_to_next_workday() {
+        let calendar = Calendar::new(Default::default(), Country::NO);
+        let friday = NaiveDate::from_ymd_opt(2020, 10, 30)
+            .unwrap()
+            .and_time(time(18, 30));
+
+        let config = Configuration::load(Path::new("config.yaml")).expect("failed");
+        let replies = config.get_replies(Locale::Sv);
+
+        let answer = get_gg_answer(&OfficeHours::default(), &calendar, &friday, &replies).await;
+
+        assert!(answer.ends_with(
+            "Nästa arbetsdag börjar måndag 2 november kl 08:00, om 2 dagar och 13 timmar."
+        ));
+    }
+
+    #[tokio::test]
+    async fn answers_in_english() {
+        let calendar = Calendar::new(Default::default(), Country::NO);
+        let friday = NaiveDate::from_ymd_opt(2020, 10, 30)
+            .unwrap()
+            .and_time(time(18, 30));
+
+        let config = Configuration::load(Path::new("config.yaml")).expect("failed");
+        let replies = config.get_replies(Locale::En);
+
+        let answer = get_gg_answer(&OfficeHours::default(), &calendar, &friday, &replies).await;
+
+        assert_eq!(answer, "It's past 17:00, stay calm and stop working! The next workday starts Monday 2 November at 08:00, in 2 days and 13 hours.");
+    }
+}
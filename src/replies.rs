@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::config::{choose_message, Message};
+use crate::locale::Locale;
+use crate::template::{render, Variables, INTRO_VARIABLES};
+
+pub type ReplyPools = HashMap<Reply, Vec<Message>>;
+
+// Declares `Reply` with the key and variables of every reply in config.yaml, so the enum,
+// `REPLIES`, the keys and the variables can't get out of step
+macro_rules! replies {
+    ($($reply:ident => $key:literal $variables:expr,)*) => {
+        /// Everything joel-bot answers with, configured under `replies` in config.yaml. The
+        /// variables each reply can use are listed there.
+        #[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum Reply {
+            $(
+                #[serde(rename = $key)]
+                $reply,
+            )*
+        }
+
+        pub const REPLIES: &[Reply] = &[$(Reply::$reply,)*];
+
+        impl Reply {
+            /// The key in config.yaml, e.g. `gg_lunch`.
+            pub fn get_key(&self) -> &'static str {
+                match self {
+                    $(Reply::$reply => $key,)*
+                }
+            }
+
+            /// The variables the reply is rendered with, e.g. `date` for `last_workday`.
+            pub fn get_variables(&self) -> &'static [&'static str] {
+                match self {
+                    $(Reply::$reply => &$variables,)*
+                }
+            }
+        }
+    };
+}
+
+replies! {
+    Error => "error" [],
+    UnknownCommand => "unknown_command" ["user", "command"],
+    Pricing => "pricing" [],
+    IntroCommands => "intro_commands" INTRO_VARIABLES,
+    LastWorkday => "last_workday" ["date"],
+    LastWorkdayToday => "last_workday_today" ["date"],
+    MonthUnknown => "month_unknown" ["name"],
+    ClientUnknown => "client_unknown" ["client", "clients"],
+    ClientDeadline => "client_deadline" ["client", "period", "date"],
+    ClientDeadlineToday => "client_deadline_today" ["client", "period", "date"],
+    PeriodMonthly => "period_monthly" [],
+    PeriodWeekly => "period_weekly" ["weekday"],
+    PeriodBiweekly => "period_biweekly" ["weekday"],
+    PeriodCutoff => "period_cutoff" ["day"],
+    WorkingTime => "working_time" ["month_name", "year", "workdays", "half_days", "hours"],
+    WorkingTimeHalfDay => "working_time_half_day" ["month_name", "year", "workdays", "half_days", "hours"],
+    WorkingTimeHalfDays => "working_time_half_days" ["month_name", "year", "workdays", "half_days", "hours"],
+    PaydayToday => "payday_today" ["date", "days"],
+    PaydayTomorrow => "payday_tomorrow" ["date", "days"],
+    PaydayLater => "payday_later" ["date", "days"],
+    TimeOff => "time_off" [],
+    TimeOffHolidays => "time_off_holidays" [],
+    TimeOffHoliday => "time_off_holiday" ["name", "date", "until"],
+    TimeOffLongWeekends => "time_off_long_weekends" [],
+    TimeOffLongWeekend => "time_off_long_weekend" ["from", "to", "days", "until"],
+    TimeOffSqueezeDays => "time_off_squeeze_days" [],
+    TimeOffSqueezeDay => "time_off_squeeze_day" ["date", "days", "until"],
+    TimeOffToday => "time_off_today" [],
+    TimeOffUntil => "time_off_until" ["duration", "workdays"],
+    OfficeHours => "office_hours" ["start", "end", "lunch"],
+    OfficeHoursWithoutLunch => "office_hours_without_lunch" ["start", "end"],
+    OfficeHoursInvalidRange => "office_hours_invalid_range" ["range"],
+    OfficeHoursInvalidLunch => "office_hours_invalid_lunch" ["range"],
+    OfficeHoursSetUsage => "office_hours_set_usage" [],
+    OfficeHoursSet => "office_hours_set" ["start", "end"],
+    OfficeHoursReset => "office_hours_reset" [],
+    OfficeHoursUsage => "office_hours_usage" [],
+    GgBeforeStart => "gg_before_start" ["duration"],
+    GgLunch => "gg_lunch" ["duration"],
+    GgWorking => "gg_working" ["duration"],
+    GgAfterEnd => "gg_after_end" ["end"],
+    GgDayOff => "gg_day_off" ["day", "next_workday"],
+    GgDayOffReason => "gg_day_off_reason" ["reason"],
+    GgNextWorkday => "gg_next_workday" ["date", "time", "duration"],
+    SlashLooking => "slash_looking" [],
+    SlashLastWorkday => "slash_last_workday" ["date"],
+    SlashLastWorkdayToday => "slash_last_workday_today" ["date"],
+    SlashToday => "slash_today" [],
+    SlashFailed => "slash_failed" [],
+    SlashUnknownCommand => "slash_unknown_command" ["command"],
+    AdminOnly => "admin_only" ["user"],
+    AdminUnverified => "admin_unverified" [],
+    AdminUsage => "admin_usage" [],
+    AdminMessageInvalidPart => "admin_message_invalid_part" ["name"],
+    AdminMessageInvalidContext => "admin_message_invalid_context" ["name", "contexts"],
+    AdminMessageEmpty => "admin_message_empty" [],
+    AdminMessageUnknownVariables => "admin_message_unknown_variables" ["name"],
+    AdminMessageAdded => "admin_message_added" ["id", "part", "context"],
+    AdminMessageRemoved => "admin_message_removed" ["id", "part", "context"],
+    AdminMessageNotFound => "admin_message_not_found" ["id"],
+    AdminMessageList => "admin_message_list" [],
+    AdminMessageListItem => "admin_message_list_item" ["id", "part", "context", "text", "user"],
+    AdminMessageListEmpty => "admin_message_list_empty" [],
+    CalendarName => "calendar_name" [],
+    CalendarSummary => "calendar_summary" [],
+    CalendarDescription => "calendar_description" ["month_name"],
+    CalendarAlarmDayBefore => "calendar_alarm_day_before" [],
+    CalendarAlarmToday => "calendar_alarm_today" [],
+}
+
+/// The replies in one locale, where those that aren't translated are taken from the Swedish ones.
+#[derive(Debug, Clone, Copy)]
+pub struct Replies<'a> {
+    locale: Locale,
+    localized: Option<&'a ReplyPools>,
+    default: &'a ReplyPools,
+}
+
+impl<'a> Replies<'a> {
+    pub fn new(locale: Locale, localized: Option<&'a ReplyPools>, default: &'a ReplyPools) -> Self {
+        Replies {
+            locale,
+            localized,
+            default,
+        }
+    }
+
+    pub fn get_locale(&self) -> Locale {
+        self.locale
+    }
+
+    /// Picks one of the messages for `reply` and fills in its variables.
+    pub fn get(&self, reply: Reply, variables: &Variables) -> String {
+        let messages = self
+            .localized
+            .and_then(|replies| replies.get(&reply))
+            .or_else(|| self.default.get(&reply));
+
+        match messages.and_then(|messages| choose_message(messages, &[])) {
+            Some(message) => render(message.get_text(), variables),
+            None => {
+                println!("no messages for reply {}", reply.get_key());
+                String::new()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Replies, Reply, ReplyPools, REPLIES};
+    use crate::locale::Locale;
+    use crate::template::{Variables, VARIABLES};
+
+    #[test]
+    fn replies_are_read_by_their_keys() {
+        for reply in REPLIES {
+            let pools: ReplyPools =
+                serde_yaml::from_str(&format!("{}: [Hej]", reply.get_key())).expect("failed");
+            assert_eq!(pools.keys().collect::<Vec<&Reply>>(), vec![reply]);
+        }
+    }
+
+    #[test]
+    fn reply_variables_exist() {
+        for reply in REPLIES {
+            for variable in reply.get_variables() {
+                assert!(VARIABLES.contains(variable), "{}", variable);
+            }
+        }
+    }
+
+    #[test]
+    fn untranslated_replies_are_swedish() {
+        let swedish: ReplyPools =
+            serde_yaml::from_str("pricing: [Dyrt]\nunknown_command: [\"Va, {{command}}?\"]")
+                .expect("failed");
+        let english: ReplyPools = serde_yaml::from_str("pricing: [Expensive]").expect("failed");
+        let replies = Replies::new(Locale::En, Some(&english), &swedish);
+        let variables = Variables::default().with("command", String::from("hej"));
+
+        assert_eq!(replies.get(Reply::Pricing, &variables), "Expensive");
+        assert_eq!(replies.get(Reply::UnknownCommand, &variables), "Va, hej?");
+    }
+}
@@ -0,0 +1,250 @@
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+use serde::Deserialize;
+
+use crate::format::weekday_name;
+use crate::last_day::Calendar;
+use crate::replies::{Replies, Reply};
+use crate::template::Variables;
+
+// A period ending on a work free day may have its deadline before today, but never twice in a row
+const MAX_PERIODS: usize = 3;
+
+/// How often a client wants time reports. The deadline is always the last workday on or before
+/// the end of the period.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ReportingPeriod {
+    #[default]
+    Monthly,
+    /// Every week, ending on `weekday`
+    Weekly { weekday: Weekday },
+    /// Every other week, ending on the same weekday as `anchor` which is any period end
+    Biweekly { anchor: NaiveDate },
+    /// Broken months ending on `day`, e.g. 25 for periods from the 26th to the 25th
+    Cutoff { day: u32 },
+}
+
+impl ReportingPeriod {
+    /// The last day of the period containing `date`.
+    pub fn get_period_end(&self, date: &NaiveDate) -> NaiveDate {
+        match self {
+            ReportingPeriod::Monthly => last_day_of_month(date),
+            ReportingPeriod::Weekly { weekday } => {
+                let offset = (7 + weekday.num_days_from_monday()
+                    - date.weekday().num_days_from_monday())
+                    % 7;
+                *date + Days::new(offset as u64)
+            }
+            ReportingPeriod::Biweekly { anchor } => {
+                let offset = (*anchor - *date).num_days().rem_euclid(14);
+                *date + Days::new(offset as u64)
+            }
+            ReportingPeriod::Cutoff { day } => {
+                let cutoff = cutoff_in_month(date, *day);
+                if *date <= cutoff {
+                    cutoff
+                } else {
+                    cutoff_in_month(&(date.with_day(1).unwrap() + Months::new(1)), *day)
+                }
+            }
+        }
+    }
+
+    /// The key of the period type in config.yaml, e.g. `weekly`.
+    pub fn get_kind(&self) -> &'static str {
+        match self {
+            ReportingPeriod::Monthly => "monthly",
+            ReportingPeriod::Weekly { .. } => "weekly",
+            ReportingPeriod::Biweekly { .. } => "biweekly",
+            ReportingPeriod::Cutoff { .. } => "cutoff",
+        }
+    }
+
+    /// Describes the period, e.g. "veckovis på fredagar" or "weekly on Fridays".
+    pub fn describe(&self, replies: &Replies) -> String {
+        let weekday = |weekday| weekday_name(weekday, replies.get_locale()).to_string();
+
+        match self {
+            ReportingPeriod::Monthly => replies.get(Reply::PeriodMonthly, &Variables::default()),
+            ReportingPeriod::Weekly { weekday: day } => replies.get(
+                Reply::PeriodWeekly,
+                &Variables::default().with("weekday", weekday(day)),
+            ),
+            ReportingPeriod::Biweekly { anchor } => replies.get(
+                Reply::PeriodBiweekly,
+                &Variables::default().with("weekday", weekday(&anchor.weekday())),
+            ),
+            ReportingPeriod::Cutoff { day } => replies.get(
+                Reply::PeriodCutoff,
+                &Variables::default().with("day", day.to_string()),
+            ),
+        }
+    }
+}
+
+/// The next time report deadline on or after `today`.
+pub async fn get_deadline(
+    calendar: &Calendar,
+    period: &ReportingPeriod,
+    today: &NaiveDate,
+) -> Result<NaiveDate> {
+    let mut period_end = period.get_period_end(today);
+
+    for _ in 0..MAX_PERIODS {
+        let deadline = get_workday_on_or_before(calendar, &period_end).await?;
+
+        // The deadline may have passed if the period ends on a work free day
+        if deadline >= *today {
+            return Ok(deadline);
+        }
+        period_end = period.get_period_end(&period_end.succ_opt().unwrap());
+    }
+
+    Err(anyhow!(
+        "no deadline found within {} periods from {}",
+        MAX_PERIODS,
+        today
+    ))
+}
+
+// Looks in the month of `date` and only fetches the month before when it starts with free days
+async fn get_workday_on_or_before(calendar: &Calendar, date: &NaiveDate) -> Result<NaiveDate> {
+    let workday = calendar
+        .get_month(date)
+        .await?
+        .iter()
+        .rfind(|day| day.date <= *date && day.is_workday())
+        .map(|day| day.date);
+
+    match workday {
+        Some(workday) => Ok(workday),
+        None => {
+            let previous_month = date.with_day(1).unwrap() - Months::new(1);
+            calendar.get_last_workday(&previous_month).await
+        }
+    }
+}
+
+fn last_day_of_month(date: &NaiveDate) -> NaiveDate {
+    (date.with_day(1).unwrap() + Months::new(1))
+        .pred_opt()
+        .unwrap()
+}
+
+// Months shorter than the cutoff day end on their last day instead
+fn cutoff_in_month(date: &NaiveDate, day: u32) -> NaiveDate {
+    date.with_day(day)
+        .unwrap_or_else(|| last_day_of_month(date))
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{NaiveDate, Weekday};
+
+    use super::{get_deadline, ReportingPeriod};
+    use crate::config::{Payday, WorkingTime};
+    use crate::holidays::Country;
+    use crate::last_day::Calendar;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn period_ends() {
+        let weekly = ReportingPeriod::Weekly {
+            weekday: Weekday::Fri,
+        };
+        assert_eq!(
+            weekly.get_period_end(&date(2020, 10, 26)),
+            date(2020, 10, 30)
+        );
+        assert_eq!(
+            weekly.get_period_end(&date(2020, 10, 30)),
+            date(2020, 10, 30)
+        );
+
+        let biweekly = ReportingPeriod::Biweekly {
+            anchor: date(2020, 10, 16),
+        };
+        assert_eq!(
+            biweekly.get_period_end(&date(2020, 10, 17)),
+            date(2020, 10, 30)
+        );
+        assert_eq!(
+            biweekly.get_period_end(&date(2020, 11, 5)),
+            date(2020, 11, 13)
+        );
+
+        let cutoff = ReportingPeriod::Cutoff { day: 25 };
+        assert_eq!(
+            cutoff.get_period_end(&date(2020, 10, 25)),
+            date(2020, 10, 25)
+        );
+        assert_eq!(
+            cutoff.get_period_end(&date(2020, 10, 26)),
+            date(2020, 11, 25)
+        );
+
+        let end_of_february = ReportingPeriod::Cutoff { day: 30 };
+        assert_eq!(
+            end_of_february.get_period_end(&date(2021, 2, 3)),
+            date(2021, 2, 28)
+        );
+    }
+
+    #[tokio::test]
+    async fn deadlines_move_to_previous_workday() {
+        let calendar = Calendar::new(WorkingTime::default(), Country::NO);
+
+        // Arbeidernes dag is a Friday in 2020
+        let weekly = ReportingPeriod::Weekly {
+            weekday: Weekday::Fri,
+        };
+        let deadline = get_deadline(&calendar, &weekly, &date(2020, 4, 27))
+            .await
+            .expect("failed");
+        assert_eq!(deadline, date(2020, 4, 30));
+
+        // The 25th of October 2020 is a Sunday
+        let cutoff = ReportingPeriod::Cutoff { day: 25 };
+        let deadline = get_deadline(&calendar, &cutoff, &date(2020, 10, 24))
+            .await
+            .expect("failed");
+        assert_eq!(deadline, date(2020, 11, 25));
+        let deadline = get_deadline(&calendar, &cutoff, &date(2020, 10, 20))
+            .await
+            .expect("failed");
+        assert_eq!(deadline, date(2020, 10, 23));
+    }
+
+    #[tokio::test]
+    async fn payday_is_the_workday_before_a_free_25th() {
+        let calendar = Calendar::new(WorkingTime::default(), Country::NO);
+        let payday = Payday::default().get_period();
+
+        // The 25th of July 2020 is a Saturday
+        let deadline = get_deadline(&calendar, &payday, &date(2020, 7, 1))
+            .await
+            .expect("failed");
+        assert_eq!(deadline, date(2020, 7, 24));
+        // and the 25th of December 2019, Første juledag, a Wednesday
+        let deadline = get_deadline(&calendar, &payday, &date(2019, 12, 1))
+            .await
+            .expect("failed");
+        assert_eq!(deadline, date(2019, 12, 24));
+    }
+
+    #[tokio::test]
+    async fn deadlines_may_be_in_the_month_before() {
+        let calendar = Calendar::new(WorkingTime::default(), Country::NO);
+
+        // Nyttårsdag is a Friday in 2021
+        let cutoff = ReportingPeriod::Cutoff { day: 1 };
+        let deadline = get_deadline(&calendar, &cutoff, &date(2020, 12, 30))
+            .await
+            .expect("failed");
+        assert_eq!(deadline, date(2020, 12, 31));
+    }
+}
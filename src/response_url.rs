@@ -0,0 +1,202 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use reqwest::Client;
+use serde::Serialize;
+
+use crate::slash::ResponseType;
+
+// Slack only accepts responses for a while, and only a few of them per command
+const RESPONSE_URL_TTL: Duration = Duration::from_secs(30 * 60);
+const MAX_RESPONSES: usize = 5;
+
+#[derive(Debug)]
+pub enum ResponseUrlError {
+    /// More than 30 minutes have passed since the command was sent
+    Expired,
+    /// The response_url has already been used five times
+    LimitReached,
+    Request(reqwest::Error),
+    /// Slack answered with something other than a success
+    Rejected {
+        status: u16,
+        body: String,
+    },
+}
+
+impl fmt::Display for ResponseUrlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResponseUrlError::Expired => write!(f, "response_url has expired"),
+            ResponseUrlError::LimitReached => {
+                write!(f, "response_url has been used {} times", MAX_RESPONSES)
+            }
+            ResponseUrlError::Request(error) => write!(f, "couldn't send response: {}", error),
+            ResponseUrlError::Rejected { status, body } => {
+                write!(f, "response was rejected with {}: {}", status, body)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ResponseUrlError {}
+
+impl From<reqwest::Error> for ResponseUrlError {
+    fn from(error: reqwest::Error) -> Self {
+        ResponseUrlError::Request(error)
+    }
+}
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+struct ResponseUrlMessage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_type: Option<ResponseType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    replace_original: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    delete_original: bool,
+}
+
+/// Deferred answers to a slash command, posted to its `response_url`.
+///
+/// More information here: https://api.slack.com/interactivity/handling#message_responses
+pub struct ResponseUrl {
+    client: Client,
+    url: String,
+    response_type: ResponseType,
+    received: Instant,
+    sent: usize,
+}
+
+impl ResponseUrl {
+    /// `response_type` is used for every message, create this as soon as the command is received.
+    pub fn new(url: &str, response_type: ResponseType) -> Self {
+        ResponseUrl {
+            client: Client::new(),
+            url: url.to_string(),
+            response_type,
+            received: Instant::now(),
+            sent: 0,
+        }
+    }
+
+    /// Responses left before Slack stops accepting them.
+    pub fn remaining(&self) -> usize {
+        if self.received.elapsed() >= RESPONSE_URL_TTL {
+            0
+        } else {
+            MAX_RESPONSES.saturating_sub(self.sent)
+        }
+    }
+
+    /// Posts a new message.
+    pub async fn send(&mut self, text: &str) -> Result<(), ResponseUrlError> {
+        self.post(ResponseUrlMessage {
+            response_type: Some(self.response_type),
+            text: Some(text.to_string()),
+            ..Default::default()
+        })
+        .await
+    }
+
+    /// Replaces the message the command was last answered with.
+    pub async fn replace(&mut self, text: &str) -> Result<(), ResponseUrlError> {
+        self.post(ResponseUrlMessage {
+            response_type: Some(self.response_type),
+            text: Some(text.to_string()),
+            replace_original: true,
+            ..Default::default()
+        })
+        .await
+    }
+
+    /// Deletes the message the command was last answered with.
+    pub async fn delete(&mut self) -> Result<(), ResponseUrlError> {
+        self.post(ResponseUrlMessage {
+            delete_original: true,
+            ..Default::default()
+        })
+        .await
+    }
+
+    async fn post(&mut self, message: ResponseUrlMessage) -> Result<(), ResponseUrlError> {
+        if self.received.elapsed() >= RESPONSE_URL_TTL {
+            return Err(ResponseUrlError::Expired);
+        }
+        if self.sent >= MAX_RESPONSES {
+            return Err(ResponseUrlError::LimitReached);
+        }
+
+        self.sent += 1;
+        let response = self.client.post(&self.url).json(&message).send().await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(ResponseUrlError::Rejected {
+                status: response.status().as_u16(),
+                body: response.text().await.unwrap_or_default(),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::{ResponseUrl, ResponseUrlError, ResponseUrlMessage};
+    use crate::slash::ResponseType;
+
+    const URL: &str = "https://hooks.slack.com/commands/T123/1/abc";
+
+    #[tokio::test]
+    async fn limits_are_checked_before_sending() {
+        let mut response_url = ResponseUrl::new(URL, ResponseType::Ephemeral);
+        assert_eq!(response_url.remaining(), 5);
+
+        response_url.sent = 5;
+        assert_eq!(response_url.remaining(), 0);
+        assert!(matches!(
+            response_url.send("hej").await,
+            Err(ResponseUrlError::LimitReached)
+        ));
+
+        // Instant can't go back further than the machine has been running
+        let Some(received) = Instant::now().checked_sub(Duration::from_secs(31 * 60)) else {
+            return;
+        };
+        response_url.sent = 0;
+        response_url.received = received;
+        assert_eq!(response_url.remaining(), 0);
+        assert!(matches!(
+            response_url.delete().await,
+            Err(ResponseUrlError::Expired)
+        ));
+    }
+
+    #[test]
+    fn only_used_fields_are_sent() {
+        let replace = ResponseUrlMessage {
+            response_type: Some(ResponseType::InChannel),
+            text: Some(String::from("hej")),
+            replace_original: true,
+            ..Default::default()
+        };
+        let delete = ResponseUrlMessage {
+            delete_original: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            serde_json::to_string(&replace).expect("failed"),
+            r#"{"response_type":"in_channel","text":"hej","replace_original":true}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&delete).expect("failed"),
+            r#"{"delete_original":true}"#
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use std::error::Error;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use tokio::signal::unix::{signal, SignalKind};
+
+use crate::config::Configuration;
+use crate::config_source::ConfigSource;
+
+// How often the configuration files are checked for changes
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The configuration in use, replaced as a whole when the file changes.
+pub struct SharedConfiguration {
+    source: ConfigSource,
+    current: RwLock<Arc<Configuration>>,
+}
+
+impl SharedConfiguration {
+    pub fn load(source: ConfigSource) -> Result<SharedConfiguration, Box<dyn Error>> {
+        let config = source.load()?;
+        Ok(SharedConfiguration {
+            source,
+            current: RwLock::new(Arc::new(config)),
+        })
+    }
+
+    /// The current configuration, kept as is for as long as the caller holds on to it.
+    pub fn get(&self) -> Arc<Configuration> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Replaces the configuration if the file is valid, otherwise the current one is kept.
+    pub fn reload(&self) -> Result<(), Box<dyn Error>> {
+        let config = self.source.load()?;
+        *self.current.write().unwrap() = Arc::new(config);
+        Ok(())
+    }
+
+    /// Reloads the configuration when any of the files change or the process gets a SIGHUP.
+    pub async fn watch(self: Arc<Self>) {
+        let mut hangup = match signal(SignalKind::hangup()) {
+            Ok(hangup) => hangup,
+            Err(error) => {
+                println!("couldn't listen for SIGHUP: {}", error);
+                return;
+            }
+        };
+        let mut interval = tokio::time::interval(WATCH_INTERVAL);
+        let mut modified = self.get_modified();
+
+        loop {
+            tokio::select! {
+                _ = hangup.recv() => {}
+                _ = interval.tick() => {
+                    if self.get_modified() == modified {
+                        continue;
+                    }
+                }
+            }
+            modified = self.get_modified();
+
+            match self.reload() {
+                Ok(()) => println!("reloaded configuration"),
+                Err(error) => println!(
+                    "keeping the current configuration, the new one is invalid: {}",
+                    error
+                ),
+            }
+        }
+    }
+
+    fn get_modified(&self) -> Vec<Option<SystemTime>> {
+        self.source
+            .get_paths()
+            .iter()
+            .map(|path| {
+                std::fs::metadata(path)
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::path::PathBuf;
+
+    use super::SharedConfiguration;
+    use crate::config_source::ConfigSource;
+
+    #[test]
+    fn invalid_configuration_is_not_loaded() {
+        let path =
+            std::env::temp_dir().join(format!("joel-bot-config-{}.yaml", std::process::id()));
+        let config = std::fs::read_to_string("config.yaml").expect("failed to read");
+        std::fs::write(&path, &config).expect("failed to write");
+
+        let shared = SharedConfiguration::load(ConfigSource::new(path.clone(), None))
+            .expect("failed to load");
+        let before = shared.get();
+
+        let broken = config.replace("  beginning:\n    general:", "  beginning:\n    jul:");
+        std::fs::write(&path, broken).expect("failed to write");
+        let reloaded = shared.reload();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(reloaded.is_err());
+        assert!(std::sync::Arc::ptr_eq(&before, &shared.get()));
+        assert!(
+            SharedConfiguration::load(ConfigSource::new(PathBuf::from("missing.yaml"), None))
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use chrono::Utc;
+use ring::hmac;
+use rocket::data::{self, Data, FromData, ToByteUnit};
+use rocket::http::Status;
+use rocket::outcome::Outcome;
+use rocket::request::Request;
+use serde::de::DeserializeOwned;
+
+// More information here: https://api.slack.com/authentication/verifying-requests-from-slack
+const SIGNATURE_VERSION: &str = "v0";
+// Older requests are refused so that a captured request can't be sent again
+const MAX_REQUEST_AGE_SECONDS: i64 = 5 * 60;
+const MAX_BODY_KIB: u64 = 512;
+
+/// The signing secret of the Slack app, read from `JOEL_BOT_SLACK_SIGNING_SECRET`.
+pub struct SigningSecret(Option<String>);
+
+impl SigningSecret {
+    pub fn new(secret: Option<String>) -> Self {
+        SigningSecret(secret.filter(|secret| !secret.is_empty()))
+    }
+
+    pub fn from_env() -> Self {
+        SigningSecret::new(std::env::var("JOEL_BOT_SLACK_SIGNING_SECRET").ok())
+    }
+
+    pub fn is_configured(&self) -> bool {
+        self.0.is_some()
+    }
+}
+
+/// Whether `signature` is what Slack signs `body` with when it is sent at `timestamp`, no more than
+/// five minutes from `now`.
+pub fn is_signed(secret: &str, timestamp: &str, body: &[u8], signature: &str, now: i64) -> bool {
+    let is_recent = timestamp
+        .parse::<i64>()
+        .is_ok_and(|sent| (now - sent).abs() <= MAX_REQUEST_AGE_SECONDS);
+    let signature = signature
+        .strip_prefix(SIGNATURE_VERSION)
+        .and_then(|signature| signature.strip_prefix('='))
+        .and_then(decode_hex);
+
+    match signature {
+        Some(signature) if is_recent => {
+            let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+            let message = [
+                SIGNATURE_VERSION.as_bytes(),
+                b":",
+                timestamp.as_bytes(),
+                b":",
+                body,
+            ]
+            .concat();
+            hmac::verify(&key, &message, &signature).is_ok()
+        }
+        _ => false,
+    }
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(text.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+/// A JSON body from Slack. Requests with a wrong signature are refused with 401 when there is a
+/// [`SigningSecret`], and without one the body is let through with `verified` set to false.
+pub struct SlackSigned<T> {
+    pub value: T,
+    pub verified: bool,
+}
+
+#[rocket::async_trait]
+impl<'r, T: DeserializeOwned> FromData<'r> for SlackSigned<T> {
+    type Error = String;
+
+    async fn from_data(request: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+        let body = match data.open(MAX_BODY_KIB.kibibytes()).into_bytes().await {
+            Ok(body) if body.is_complete() => body.into_inner(),
+            Ok(_) => {
+                return Outcome::Error((Status::PayloadTooLarge, String::from("too large")));
+            }
+            Err(error) => return Outcome::Error((Status::BadRequest, error.to_string())),
+        };
+
+        let secret = request
+            .rocket()
+            .state::<SigningSecret>()
+            .and_then(|secret| secret.0.as_deref());
+        let verified = match secret {
+            Some(secret) => {
+                let header = |name| request.headers().get_one(name).unwrap_or_default();
+                let timestamp = header("X-Slack-Request-Timestamp");
+                let signature = header("X-Slack-Signature");
+                if !is_signed(secret, timestamp, &body, signature, Utc::now().timestamp()) {
+                    println!("refusing a request that isn't signed by Slack");
+                    return Outcome::Error((Status::Unauthorized, String::from("not signed")));
+                }
+                true
+            }
+            None => false,
+        };
+
+        match serde_json::from_slice(&body) {
+            Ok(value) => Outcome::Success(SlackSigned { value, verified }),
+            Err(error) => Outcome::Error((Status::UnprocessableEntity, error.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+    use ring::hmac;
+    use rocket::http::{Header, Status};
+    use rocket::local::asynchronous::Client;
+
+    use super::{is_signed, SigningSecret, SlackSigned};
+
+    const SECRET: &str = "8f742231b10e8888abcd99yyyzzz85a5";
+    const BODY: &str = "{\"type\":\"event_callback\"}";
+
+    fn sign(secret: &str, timestamp: &str, body: &str) -> String {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+        let tag = hmac::sign(&key, format!("v0:{}:{}", timestamp, body).as_bytes());
+        let hex: String = tag
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        format!("v0={}", hex)
+    }
+
+    #[rocket::post("/", data = "<request>")]
+    fn signed(request: SlackSigned<serde_json::Value>) -> String {
+        request.verified.to_string()
+    }
+
+    async fn post(secret: Option<&str>, timestamp: i64, signature: &str) -> (Status, String) {
+        let rocket = rocket::build()
+            .manage(SigningSecret::new(secret.map(String::from)))
+            .mount("/", rocket::routes![signed]);
+        let client = Client::tracked(rocket).await.expect("failed");
+        let response = client
+            .post("/")
+            .header(Header::new(
+                "X-Slack-Request-Timestamp",
+                timestamp.to_string(),
+            ))
+            .header(Header::new("X-Slack-Signature", signature.to_string()))
+            .body(BODY)
+            .dispatch()
+            .await;
+        let status = response.status();
+        (status, response.into_string().await.unwrap_or_default())
+    }
+
+    #[test]
+    fn only_recent_requests_signed_with_the_secret_are_accepted() {
+        let now = 1_600_000_000;
+        let timestamp = now.to_string();
+        let signature = sign(SECRET, &timestamp, BODY);
+
+        let is_signed_with = |secret, body: &[u8], signature: &str, now| {
+            is_signed(secret, &timestamp, body, signature, now)
+        };
+
+        assert!(is_signed_with(SECRET, BODY.as_bytes(), &signature, now));
+        assert!(!is_signed_with(SECRET, b"{}", &signature, now));
+        assert!(!is_signed_with("another", BODY.as_bytes(), &signature, now));
+        assert!(!is_signed_with(
+            SECRET,
+            BODY.as_bytes(),
+            &signature,
+            now + 301
+        ));
+        assert!(!is_signed_with(SECRET, BODY.as_bytes(), "v0=zz", now));
+    }
+
+    #[rocket::async_test]
+    async fn forged_requests_are_refused() {
+        let now = Utc::now().timestamp();
+        let signature = sign(SECRET, &now.to_string(), BODY);
+        let forged = sign("guessed", &now.to_string(), BODY);
+
+        assert_eq!(
+            post(Some(SECRET), now, &signature).await,
+            (Status::Ok, String::from("true"))
+        );
+        assert_eq!(
+            post(Some(SECRET), now, &forged).await.0,
+            Status::Unauthorized
+        );
+        assert_eq!(
+            post(None, now, &forged).await,
+            (Status::Ok, String::from("false"))
+        );
+    }
+}
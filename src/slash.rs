@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use rocket::FromForm;
+use serde::Serialize;
+use tokio::time::sleep;
+
+use crate::client::{get_user_timezone, SlackClient};
+use crate::config::Configuration;
+use crate::office_hours::get_gg_answer;
+use crate::replies::{Replies, Reply};
+use crate::response_url::ResponseUrl;
+use crate::shared_config::SharedConfiguration;
+use crate::storage::Storage;
+use crate::template::Variables;
+
+// Adding this to a command shares the answer with the whole channel, e.g. `/gg alla`
+const IN_CHANNEL_ARGUMENT: &str = "alla";
+
+/// The payload Slack posts for a slash command.
+///
+/// More information here: https://api.slack.com/interactivity/slash-commands
+#[derive(FromForm, Debug, Clone)]
+pub struct SlashCommand {
+    // TODO: Verify the token then allow the request.
+    pub token: String,
+    /// The command that was typed, e.g. `/gg`
+    pub command: String,
+    /// Everything after the command, empty if nothing was given
+    #[field(default = String::new())]
+    pub text: String,
+    pub user_id: String,
+    pub user_name: Option<String>,
+    pub channel_id: String,
+    pub channel_name: Option<String>,
+    pub team_id: String,
+    pub team_domain: Option<String>,
+    pub enterprise_id: Option<String>,
+    pub api_app_id: Option<String>,
+    pub trigger_id: String,
+    /// Where delayed answers are posted, valid for 30 minutes
+    pub response_url: String,
+}
+
+impl SlashCommand {
+    pub fn arguments(&self) -> Vec<&str> {
+        self.text.split_whitespace().collect()
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseType {
+    /// Visible to everyone in the channel
+    InChannel,
+    /// Only visible to the user who typed the command
+    Ephemeral,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct SlashResponse {
+    pub response_type: ResponseType,
+    pub text: String,
+}
+
+impl SlashResponse {
+    pub fn new(response_type: ResponseType, text: String) -> Self {
+        SlashResponse {
+            response_type,
+            text,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    TimeReport,
+    Gg,
+}
+
+/// Maps the slash commands set up in Slack to what joel-bot does for them.
+pub struct CommandRegistry {
+    commands: HashMap<String, (Command, ResponseType)>,
+}
+
+impl Default for CommandRegistry {
+    fn default() -> Self {
+        let mut registry = CommandRegistry {
+            commands: HashMap::new(),
+        };
+        registry.register("/joel", Command::TimeReport, ResponseType::Ephemeral);
+        registry.register("/time-report", Command::TimeReport, ResponseType::Ephemeral);
+        registry.register("/gg", Command::Gg, ResponseType::Ephemeral);
+        registry
+    }
+}
+
+impl CommandRegistry {
+    pub fn register(&mut self, name: &str, command: Command, response_type: ResponseType) {
+        self.commands
+            .insert(name.to_lowercase(), (command, response_type));
+    }
+
+    pub fn get(&self, name: &str) -> Option<(Command, ResponseType)> {
+        self.commands.get(&name.to_lowercase()).copied()
+    }
+
+    /// Runs the command registered for `request.command`.
+    pub async fn dispatch(&self, context: &SlashContext, request: SlashCommand) -> SlashResponse {
+        match self.get(&request.command) {
+            Some((command, response_type)) => context.run(command, response_type, request).await,
+            None => {
+                let config = context.config.get();
+                let replies = get_replies(&config, &request);
+                let variables = Variables::default().with("command", request.command.clone());
+                SlashResponse::new(
+                    ResponseType::Ephemeral,
+                    replies.get(Reply::SlashUnknownCommand, &variables),
+                )
+            }
+        }
+    }
+}
+
+/// What the slash commands need to answer.
+#[derive(Clone)]
+pub struct SlashContext {
+    pub config: Arc<SharedConfiguration>,
+    pub client: Arc<SlackClient>,
+    pub storage: Arc<Storage>,
+}
+
+impl SlashContext {
+    pub async fn run(
+        &self,
+        command: Command,
+        response_type: ResponseType,
+        request: SlashCommand,
+    ) -> SlashResponse {
+        let response_type = get_response_type(&request, response_type);
+
+        match command {
+            Command::TimeReport => self.time_report(response_type, request).await,
+            Command::Gg => self.gg(response_type, request).await,
+        }
+    }
+
+    async fn time_report(
+        &self,
+        response_type: ResponseType,
+        request: SlashCommand,
+    ) -> SlashResponse {
+        let config = self.config.get();
+        let client = self.client.clone();
+        let mut response_url = ResponseUrl::new(&request.response_url, response_type);
+
+        let replies = get_replies(&config, &request);
+        let calculating = config.get_calculating(replies.get_locale());
+        let looking = replies.get(Reply::SlashLooking, &Variables::default());
+
+        // Slack only waits 3 seconds for the first answer, so the lookups are done after it
+        tokio::spawn(async move {
+            let timezone = get_user_timezone(client.as_ref(), Some(&request.user_id)).await;
+            let calendar =
+                config.get_calendar_for(Some(&request.user_id), Some(&request.channel_id));
+            let replies = get_replies(&config, &request);
+            let today = Utc::now().with_timezone(&timezone).date_naive();
+            let delay = calculating.get_delay();
+
+            match calendar.get_last_workday(&today).await {
+                Ok(last_workday) => {
+                    let variables = Variables::default().with("date", last_workday.to_string());
+                    if last_workday == today {
+                        let mut text = replies.get(Reply::SlashLastWorkdayToday, &variables);
+                        replace_after_calculating(&mut response_url, &text, delay).await;
+
+                        for phrase in calculating.get_phrases() {
+                            // Save the last response for the actual answer
+                            if response_url.remaining() <= 1 {
+                                break;
+                            }
+                            text = format!("{}\n... {}", text, phrase);
+                            replace_after_calculating(&mut response_url, &text, delay).await;
+                        }
+
+                        text = format!(
+                            "{}\n... {}",
+                            text,
+                            replies.get(Reply::SlashToday, &variables)
+                        );
+                        replace_after_calculating(&mut response_url, &text, delay).await;
+                    } else {
+                        let text = replies.get(Reply::SlashLastWorkday, &variables);
+                        replace_after_calculating(&mut response_url, &text, delay).await;
+                    }
+                }
+                Err(error) => {
+                    println!("failed to get last work day: {}", error);
+                    let text = replies.get(Reply::SlashFailed, &Variables::default());
+                    replace_after_calculating(&mut response_url, &text, delay).await;
+                }
+            };
+        });
+
+        SlashResponse::new(response_type, looking)
+    }
+
+    async fn gg(&self, response_type: ResponseType, request: SlashCommand) -> SlashResponse {
+        let config = self.config.get();
+        let looking =
+            get_replies(&config, &request).get(Reply::SlashLooking, &Variables::default());
+        let context = self.clone();
+        let mut response_url = ResponseUrl::new(&request.response_url, response_type);
+
+        // Slack only waits 3 seconds for the first answer, so the lookups are done after it
+        tokio::spawn(async move {
+            let answer = context.get_gg_answer(&request).await;
+            if let Err(error) = response_url.replace(&answer).await {
+                println!("failed to update message: {}", error);
+            }
+        });
+
+        SlashResponse::new(response_type, looking)
+    }
+
+    async fn get_gg_answer(&self, request: &SlashCommand) -> String {
+        let config = self.config.get();
+        let calendar = config.get_calendar_for(Some(&request.user_id), Some(&request.channel_id));
+        let office_hours = self
+            .storage
+            .get_office_hours(&request.user_id)
+            .await
+            .unwrap_or_else(|| config.get_working_time().office_hours.clone());
+
+        let timezone = get_user_timezone(self.client.as_ref(), Some(&request.user_id)).await;
+        let now = Utc::now().with_timezone(&timezone).naive_local();
+        let replies = get_replies(&config, request);
+
+        get_gg_answer(&office_hours, &calendar, &now, &replies).await
+    }
+}
+
+// Answers in the language of the user, or the channel the command was typed in
+fn get_replies<'a>(config: &'a Configuration, request: &SlashCommand) -> Replies<'a> {
+    config.get_replies(config.get_locale_for(Some(&request.user_id), Some(&request.channel_id)))
+}
+
+fn get_response_type(request: &SlashCommand, default: ResponseType) -> ResponseType {
+    if request
+        .arguments()
+        .iter()
+        .any(|argument| argument.eq_ignore_ascii_case(IN_CHANNEL_ARGUMENT))
+    {
+        ResponseType::InChannel
+    } else {
+        default
+    }
+}
+
+// Updates the "Ska ta en titt i kalendern..." answer in place
+async fn replace_after_calculating(response_url: &mut ResponseUrl, text: &str, delay: Duration) {
+    // To "fool" the user that we are actually calculating something
+    sleep(delay).await;
+
+    if let Err(error) = response_url.replace(text).await {
+        println!("failed to update message: {}", error);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{get_response_type, Command, CommandRegistry, ResponseType, SlashCommand};
+
+    fn request(command: &str, text: &str) -> SlashCommand {
+        SlashCommand {
+            token: String::from("token"),
+            command: String::from(command),
+            text: String::from(text),
+            user_id: String::from("U123"),
+            user_name: None,
+            channel_id: String::from("C123"),
+            channel_name: None,
+            team_id: String::from("T123"),
+            team_domain: None,
+            enterprise_id: None,
+            api_app_id: None,
+            trigger_id: String::from("123.456"),
+            response_url: String::from("https://hooks.slack.com/commands/T123/1/abc"),
+        }
+    }
+
+    #[test]
+    fn commands_are_looked_up_by_name() {
+        let registry = CommandRegistry::default();
+
+        assert_eq!(
+            registry.get("/GG"),
+            Some((Command::Gg, ResponseType::Ephemeral))
+        );
+        assert_eq!(
+            registry.get("/joel").map(|(command, _)| command),
+            Some(Command::TimeReport)
+        );
+        assert_eq!(registry.get("/okänt"), None);
+    }
+
+    #[test]
+    fn answers_can_be_shared_with_the_channel() {
+        assert_eq!(
+            get_response_type(&request("/gg", ""), ResponseType::Ephemeral),
+            ResponseType::Ephemeral
+        );
+        assert_eq!(
+            get_response_type(&request("/gg", "alla"), ResponseType::Ephemeral),
+            ResponseType::InChannel
+        );
+        assert_eq!(
+            serde_json::to_string(&ResponseType::InChannel).expect("failed"),
+            "\"in_channel\""
+        );
+    }
+}
@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tokio::task::spawn_blocking;
+
+use crate::office_hours::OfficeHours;
+
+const DEFAULT_STORAGE_PATH: &str = "storage.json";
+
+// The whole file is read for every request, so the audit log can't grow forever
+const MAX_AUDIT_ENTRIES: usize = 1000;
+
+/// Everything the bot remembers between restarts.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct StorageData {
+    /// Slack user id to the user's own office hours
+    #[serde(default)]
+    pub office_hours: HashMap<String, OfficeHours>,
+    /// The latest picked messages per message part, oldest first
+    #[serde(default)]
+    pub recent_messages: HashMap<String, Vec<String>>,
+    /// Time report messages added from Slack, on top of those in config.yaml
+    #[serde(default)]
+    pub messages: Vec<StoredMessage>,
+    /// Who added and removed the latest messages, oldest first
+    #[serde(default)]
+    pub audit_log: Vec<AuditEntry>,
+    /// Ids of removed messages are never reused, so the audit log stays unambiguous
+    #[serde(default)]
+    pub last_message_id: u32,
+}
+
+/// A time report message added with `admin meddelande lägg-till`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StoredMessage {
+    pub id: u32,
+    /// beginning, middle or end
+    pub part: String,
+    /// general, a month or a context like påsk
+    pub context: String,
+    pub text: String,
+    /// Slack user id
+    pub added_by: String,
+    pub added_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditAction {
+    Added,
+    Removed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    pub at: DateTime<Utc>,
+    /// Slack user id
+    pub user: String,
+    pub action: AuditAction,
+    pub message: StoredMessage,
+}
+
+impl StorageData {
+    /// Remembers `message` as picked for `key`, forgetting all but the latest `limit` ones.
+    pub fn remember_message(&mut self, key: &str, message: &str, limit: usize) {
+        let recent = self.recent_messages.entry(key.to_string()).or_default();
+        recent.push(message.to_string());
+        let excess = recent.len().saturating_sub(limit);
+        recent.drain(..excess);
+    }
+
+    pub fn add_message(
+        &mut self,
+        part: &str,
+        context: &str,
+        text: &str,
+        user: &str,
+        at: DateTime<Utc>,
+    ) -> StoredMessage {
+        // Storage written before last_message_id only has the ids in the messages and the log
+        let id = self
+            .messages
+            .iter()
+            .chain(self.audit_log.iter().map(|entry| &entry.message))
+            .map(|message| message.id)
+            .chain([self.last_message_id])
+            .max()
+            .unwrap_or(0)
+            + 1;
+        self.last_message_id = id;
+        let message = StoredMessage {
+            id,
+            part: part.to_string(),
+            context: context.to_string(),
+            text: text.to_string(),
+            added_by: user.to_string(),
+            added_at: at,
+        };
+
+        self.messages.push(message.clone());
+        self.audit(user, AuditAction::Added, &message, at);
+        message
+    }
+
+    pub fn remove_message(
+        &mut self,
+        id: u32,
+        user: &str,
+        at: DateTime<Utc>,
+    ) -> Option<StoredMessage> {
+        let index = self.messages.iter().position(|message| message.id == id)?;
+        let message = self.messages.remove(index);
+
+        self.audit(user, AuditAction::Removed, &message, at);
+        Some(message)
+    }
+
+    fn audit(
+        &mut self,
+        user: &str,
+        action: AuditAction,
+        message: &StoredMessage,
+        at: DateTime<Utc>,
+    ) {
+        self.audit_log.push(AuditEntry {
+            at,
+            user: user.to_string(),
+            action,
+            message: message.clone(),
+        });
+        let excess = self.audit_log.len().saturating_sub(MAX_AUDIT_ENTRIES);
+        self.audit_log.drain(..excess);
+    }
+}
+
+/// A JSON file with the bot's state, shared by the app and the reminder job. It's read again before
+/// every read and update, and updates hold a lock on `<path>.lock` so neither overwrites the other.
+/// The file is on a network share, so it is only touched on the blocking thread pool.
+pub struct Storage {
+    path: PathBuf,
+    data: Mutex<StorageData>,
+}
+
+impl Storage {
+    /// Opens the storage at `JOEL_BOT_STORAGE`, or `storage.json` in the working directory.
+    pub fn open() -> Result<Storage> {
+        let path = std::env::var("JOEL_BOT_STORAGE").unwrap_or_else(|_| {
+            if std::env::var("APP_ENVIRONMENT").is_ok() {
+                println!(
+                    "JOEL_BOT_STORAGE isn't set, so office hours and admin messages in {} are \
+                     lost with the container",
+                    DEFAULT_STORAGE_PATH
+                );
+            }
+            String::from(DEFAULT_STORAGE_PATH)
+        });
+        Storage::open_path(PathBuf::from(path))
+    }
+
+    pub fn open_path(path: PathBuf) -> Result<Storage> {
+        let data = Storage::read_file(&path)?;
+
+        Ok(Storage {
+            path,
+            data: Mutex::new(data),
+        })
+    }
+
+    pub async fn get_office_hours(&self, user: &str) -> Option<OfficeHours> {
+        self.read(|data| data.office_hours.get(user).cloned()).await
+    }
+
+    pub async fn get_messages(&self) -> Vec<StoredMessage> {
+        self.read(|data| data.messages.clone()).await
+    }
+
+    /// Reads the latest data, or what was read last if the file can't be read right now.
+    pub async fn read<T>(&self, reader: impl FnOnce(&StorageData) -> T) -> T {
+        let mut data = self.data.lock().await;
+        let path = self.path.clone();
+        match spawn_blocking(move || Storage::read_file(&path)).await {
+            Ok(Ok(latest)) => *data = latest,
+            Ok(Err(error)) => println!("failed to read storage, using the last read: {}", error),
+            Err(error) => println!("failed to read storage, using the last read: {}", error),
+        }
+        reader(&data)
+    }
+
+    /// Applies `update` to the latest data and persists the result. Nothing is changed if it can't
+    /// be written.
+    pub async fn update<T>(&self, update: impl FnOnce(&mut StorageData) -> T) -> Result<T> {
+        let mut data = self.data.lock().await;
+        let path = self.path.clone();
+        let (lock, mut updated) = spawn_blocking(move || -> Result<_> {
+            let lock = File::options()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(path.with_extension("lock"))?;
+            lock.lock()?;
+            Ok((lock, Storage::read_file(&path)?))
+        })
+        .await??;
+
+        let result = update(&mut updated);
+
+        let path = self.path.clone();
+        let written = updated.clone();
+        spawn_blocking(move || -> Result<()> {
+            // Write to a temporary file first so a crash never leaves half a file behind
+            let temporary = path.with_extension("tmp");
+            std::fs::write(&temporary, serde_json::to_string_pretty(&written)?)?;
+            std::fs::rename(&temporary, &path)?;
+            // Only let the other process in once the file is written
+            drop(lock);
+            Ok(())
+        })
+        .await??;
+
+        *data = updated;
+        Ok(result)
+    }
+
+    fn read_file(path: &Path) -> Result<StorageData> {
+        if !path.exists() {
+            return Ok(StorageData::default());
+        }
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{NaiveTime, Utc};
+
+    use super::{AuditAction, Storage, StorageData, MAX_AUDIT_ENTRIES};
+    use crate::office_hours::OfficeHours;
+
+    #[tokio::test]
+    async fn updates_are_persisted() {
+        let path =
+            std::env::temp_dir().join(format!("joel-bot-storage-{}.json", std::process::id()));
+        let hours = OfficeHours {
+            start: NaiveTime::from_hms_opt(7, 30, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(16, 0, 0).unwrap(),
+            lunch: None,
+        };
+
+        let storage = Storage::open_path(path.clone()).expect("failed to open");
+        storage
+            .update(|data| {
+                data.office_hours
+                    .insert(String::from("U123"), hours.clone())
+            })
+            .await
+            .expect("failed to update");
+
+        let reopened = Storage::open_path(path.clone()).expect("failed to reopen");
+        let stored = reopened.get_office_hours("U123").await;
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(path.with_extension("lock")).unwrap();
+
+        assert_eq!(stored, Some(hours));
+    }
+
+    #[tokio::test]
+    async fn updates_from_another_process_are_kept() {
+        let path = std::env::temp_dir().join(format!(
+            "joel-bot-storage-shared-{}.json",
+            std::process::id()
+        ));
+        let app = Storage::open_path(path.clone()).expect("failed to open");
+        let job = Storage::open_path(path.clone()).expect("failed to open");
+
+        app.update(|data| data.add_message("end", "general", "Hejdå", "U1", Utc::now()))
+            .await
+            .expect("failed to update");
+        job.update(|data| data.remember_message("time_report.end", "Hej", 3))
+            .await
+            .expect("failed to update");
+
+        let messages = app.get_messages().await;
+        let recent = app
+            .read(|data| data.recent_messages["time_report.end"].clone())
+            .await;
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(path.with_extension("lock")).unwrap();
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(recent, vec!["Hej"]);
+    }
+
+    #[test]
+    fn only_the_latest_messages_are_remembered() {
+        let mut data = StorageData::default();
+        for message in ["ett", "två", "tre", "fyra"] {
+            data.remember_message("time_report.end", message, 3);
+        }
+
+        assert_eq!(
+            data.recent_messages["time_report.end"],
+            vec!["två", "tre", "fyra"]
+        );
+    }
+
+    #[test]
+    fn added_and_removed_messages_are_audited() {
+        let mut data = StorageData::default();
+        let now = Utc::now();
+
+        let first = data.add_message("middle", "12", "God jul!", "U1", now);
+        data.remove_message(first.id, "U2", now).expect("failed");
+        let second = data.add_message("end", "general", "Hejdå", "U1", now);
+
+        assert_eq!(second.id, 2);
+        assert_eq!(data.messages, vec![second]);
+        assert_eq!(data.remove_message(first.id, "U2", now), None);
+
+        let log: Vec<(&str, AuditAction, u32)> = data
+            .audit_log
+            .iter()
+            .map(|entry| (entry.user.as_str(), entry.action, entry.message.id))
+            .collect();
+        assert_eq!(
+            log,
+            vec![
+                ("U1", AuditAction::Added, 1),
+                ("U2", AuditAction::Removed, 1),
+                ("U1", AuditAction::Added, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn ids_are_not_reused_after_the_audit_log_is_capped() {
+        let mut data = StorageData::default();
+        let now = Utc::now();
+
+        for _ in 0..MAX_AUDIT_ENTRIES {
+            let message = data.add_message("end", "general", "Hejdå", "U1", now);
+            data.remove_message(message.id, "U1", now).expect("failed");
+        }
+        let message = data.add_message("end", "general", "Hej", "U1", now);
+
+        assert_eq!(data.audit_log.len(), MAX_AUDIT_ENTRIES);
+        assert_eq!(message.id, MAX_AUDIT_ENTRIES as u32 + 1);
+    }
+}
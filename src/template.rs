@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chrono::{Datelike, Days, NaiveDate, NaiveTime};
+
+use crate::format::{format_date, month_name};
+use crate::last_day::Calendar;
+use crate::locale::Locale;
+
+/// The variables that can be used in reminders, e.g. `{{last_workday}}`.
+pub const REMINDER_VARIABLES: [&str; 4] = [
+    "last_workday",
+    "month_name",
+    "workdays_left",
+    "deadline_time",
+];
+
+/// The variables that can be used in the intro, which also answers the `{{user}}` who asked.
+pub const INTRO_VARIABLES: [&str; 5] = [
+    "last_workday",
+    "month_name",
+    "workdays_left",
+    "deadline_time",
+    "user",
+];
+
+/// Every variable that any message can use. Which ones a message gets depends on what it is, see
+/// [`REMINDER_VARIABLES`], [`INTRO_VARIABLES`] and
+/// [`Reply::get_variables`](crate::replies::Reply::get_variables).
+pub const VARIABLES: [&str; 34] = [
+    "last_workday",
+    "month_name",
+    "workdays_left",
+    "deadline_time",
+    "user",
+    "command",
+    "date",
+    "time",
+    "client",
+    "clients",
+    "period",
+    "weekday",
+    "day",
+    "year",
+    "workdays",
+    "half_days",
+    "hours",
+    "days",
+    "name",
+    "until",
+    "from",
+    "to",
+    "duration",
+    "start",
+    "end",
+    "lunch",
+    "range",
+    "reason",
+    "next_workday",
+    "id",
+    "part",
+    "context",
+    "contexts",
+    "text",
+];
+
+/// Values for the variables in a message. Variables without a value are rendered as nothing.
+#[derive(Debug, Default, Clone)]
+pub struct Variables {
+    values: HashMap<&'static str, String>,
+}
+
+impl Variables {
+    /// The variables for a time report deadline, as seen on `today`.
+    pub async fn for_deadline(
+        calendar: &Calendar,
+        deadline: &NaiveDate,
+        deadline_time: &NaiveTime,
+        today: &NaiveDate,
+        locale: Locale,
+    ) -> Result<Variables> {
+        let workdays_left = match today.checked_add_days(Days::new(1)) {
+            Some(tomorrow) if tomorrow <= *deadline => {
+                calendar.get_workdays(&tomorrow, deadline).await?.len()
+            }
+            _ => 0,
+        };
+
+        Ok(Variables::default()
+            .with("last_workday", format_date(deadline, locale))
+            .with("month_name", month_name(today.month(), locale).to_string())
+            .with("workdays_left", workdays_left.to_string())
+            .with("deadline_time", deadline_time.format("%H:%M").to_string()))
+    }
+
+    /// Sets `name`, which must be one of [`VARIABLES`].
+    pub fn with(mut self, name: &'static str, value: String) -> Self {
+        self.values.insert(name, value);
+        self
+    }
+
+    /// Mentions the Slack user as `{{user}}`.
+    pub fn with_user(self, user_id: &str) -> Self {
+        self.with("user", format!("<@{}>", user_id))
+    }
+}
+
+/// Replaces `{{variable}}` in `template`, leaving unknown variables as they are.
+pub fn render(template: &str, variables: &Variables) -> String {
+    let mut rendered = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let end = match rest[start..].find("}}") {
+            Some(end) => start + end,
+            None => break,
+        };
+        rendered.push_str(&rest[..start]);
+
+        let name = rest[start + 2..end].trim();
+        match VARIABLES.iter().find(|variable| **variable == name) {
+            Some(variable) => {
+                rendered.push_str(variables.values.get(variable).map_or("", String::as_str))
+            }
+            None => rendered.push_str(&rest[start..end + 2]),
+        }
+        rest = &rest[end + 2..];
+    }
+
+    rendered.push_str(rest);
+    rendered
+}
+
+/// The variables used in `template` that aren't among `known`.
+pub fn get_unknown_variables<'a>(template: &'a str, known: &[&str]) -> Vec<&'a str> {
+    let mut unknown = Vec::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let end = match rest[start..].find("}}") {
+            Some(end) => start + end,
+            None => break,
+        };
+        let name = rest[start + 2..end].trim();
+        if !known.contains(&name) {
+            unknown.push(name);
+        }
+        rest = &rest[end + 2..];
+    }
+
+    unknown
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{NaiveDate, NaiveTime};
+
+    use super::{get_unknown_variables, render, Variables, REMINDER_VARIABLES};
+    use crate::holidays::Country;
+    use crate::last_day::Calendar;
+    use crate::locale::Locale;
+
+    #[test]
+    fn variables_are_replaced() {
+        let variables = Variables::default()
+            .with("month_name", String::from("oktober"))
+            .with_user("U123");
+
+        assert_eq!(
+            render("Hej {{user}}, nu är {{ month_name }} slut!", &variables),
+            "Hej <@U123>, nu är oktober slut!"
+        );
+        assert_eq!(
+            render("Klockan {{deadline_time}} {{okänd}}", &variables),
+            "Klockan  {{okänd}}"
+        );
+        assert_eq!(render("println!(\"{}\")", &variables), "println!(\"{}\")");
+    }
+
+    #[test]
+    fn unknown_variables_are_found() {
+        assert_eq!(
+            get_unknown_variables(
+                "{{last_workday}} {{sista_dagen}} {{ user }} {{date}}",
+                &REMINDER_VARIABLES
+            ),
+            vec!["sista_dagen", "user", "date"]
+        );
+    }
+
+    #[tokio::test]
+    async fn workdays_left_until_the_deadline() {
+        let calendar = Calendar::new(Default::default(), Country::NO);
+        let deadline = NaiveDate::from_ymd_opt(2020, 10, 30).unwrap();
+        let today = NaiveDate::from_ymd_opt(2020, 10, 23).unwrap();
+        let variables = Variables::for_deadline(
+            &calendar,
+            &deadline,
+            &NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+            &today,
+            Locale::Sv,
+        )
+        .await
+        .expect("failed");
+
+        assert_eq!(
+            render(
+                "{{last_workday}} kl {{deadline_time}}, {{workdays_left}} dagar kvar i {{month_name}}",
+                &variables
+            ),
+            "fredag 30 oktober kl 17:00, 5 dagar kvar i oktober"
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use anyhow::Result;
+use chrono::{Days, NaiveDate};
+
+use crate::last_day::{Calendar, CalendarDay};
+
+// How far ahead `ledigt` looks for time off
+const LOOKAHEAD_DAYS: u64 = 120;
+
+#[derive(Debug, PartialEq)]
+pub struct UpcomingHoliday {
+    pub date: NaiveDate,
+    pub name: String,
+    /// Workdays left from today until the holiday
+    pub workdays_left: usize,
+}
+
+/// Three or more work free days in a row.
+#[derive(Debug, PartialEq)]
+pub struct LongWeekend {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub workdays_left: usize,
+}
+
+/// A single workday between two work free days, where a day off gives a long weekend.
+#[derive(Debug, PartialEq)]
+pub struct SqueezeDay {
+    pub date: NaiveDate,
+    /// Work free days in a row when taking the day off
+    pub days_off: usize,
+    pub workdays_left: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct TimeOff {
+    pub holidays: Vec<UpcomingHoliday>,
+    pub long_weekends: Vec<LongWeekend>,
+    pub squeeze_days: Vec<SqueezeDay>,
+}
+
+pub async fn get_time_off(calendar: &Calendar, today: &NaiveDate) -> Result<TimeOff> {
+    let until = *today + Days::new(LOOKAHEAD_DAYS);
+    let days = calendar.get_days(today, &until).await?;
+
+    Ok(find_time_off(&days))
+}
+
+/// Finds the time off in `days`, which must be consecutive and start with today.
+pub fn find_time_off(days: &[CalendarDay]) -> TimeOff {
+    let workdays_left = |index: usize| days[..index].iter().filter(|day| day.is_workday()).count();
+
+    let holidays = days
+        .iter()
+        .enumerate()
+        .filter_map(|(index, day)| {
+            day.holiday.as_ref().map(|name| UpcomingHoliday {
+                date: day.date,
+                name: name.clone(),
+                workdays_left: workdays_left(index),
+            })
+        })
+        .collect();
+
+    let mut long_weekends = Vec::new();
+    let mut index = 0;
+    while index < days.len() {
+        let length = free_days_from(days, index);
+        if length >= 3 {
+            long_weekends.push(LongWeekend {
+                from: days[index].date,
+                to: days[index + length - 1].date,
+                workdays_left: workdays_left(index),
+            });
+        }
+        index += length.max(1);
+    }
+
+    let squeeze_days = (1..days.len().saturating_sub(1))
+        .filter(|index| {
+            let (before, day, after) = (&days[index - 1], &days[*index], &days[index + 1]);
+            day.is_workday()
+                && !before.is_workday()
+                && !after.is_workday()
+                && (before.holiday.is_some() || after.holiday.is_some())
+        })
+        .map(|index| SqueezeDay {
+            date: days[index].date,
+            days_off: free_days_before(days, index) + 1 + free_days_from(days, index + 1),
+            workdays_left: workdays_left(index),
+        })
+        .collect();
+
+    TimeOff {
+        holidays,
+        long_weekends,
+        squeeze_days,
+    }
+}
+
+fn free_days_from(days: &[CalendarDay], index: usize) -> usize {
+    days[index..]
+        .iter()
+        .take_while(|day| !day.is_workday())
+        .count()
+}
+
+fn free_days_before(days: &[CalendarDay], index: usize) -> usize {
+    days[..index]
+        .iter()
+        .rev()
+        .take_while(|day| !day.is_workday())
+        .count()
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+
+    use super::{find_time_off, LongWeekend, SqueezeDay};
+    use crate::holidays::Country;
+    use crate::last_day::{Calendar, CalendarDay};
+
+    #[tokio::test]
+    async fn ascension_day_2020_gives_a_squeeze_day() {
+        let calendar = Calendar::new(Default::default(), Country::FI);
+        let from = NaiveDate::from_ymd_opt(2020, 5, 18).unwrap();
+        let to = NaiveDate::from_ymd_opt(2020, 5, 31).unwrap();
+        let days: Vec<CalendarDay> = calendar.get_days(&from, &to).await.expect("failed");
+
+        let time_off = find_time_off(&days);
+
+        assert_eq!(time_off.holidays[0].name, "Helatorstai");
+        assert_eq!(time_off.holidays[0].workdays_left, 3);
+        assert_eq!(
+            time_off.squeeze_days,
+            vec![SqueezeDay {
+                date: NaiveDate::from_ymd_opt(2020, 5, 22).unwrap(),
+                days_off: 4,
+                workdays_left: 3,
+            }]
+        );
+        // Helluntaipäivä is a Sunday so there is no long weekend at the end of the month
+        assert!(time_off.long_weekends.is_empty());
+    }
+
+    #[tokio::test]
+    async fn easter_2020_is_a_long_weekend() {
+        let calendar = Calendar::new(Default::default(), Country::NO);
+        let from = NaiveDate::from_ymd_opt(2020, 4, 6).unwrap();
+        let to = NaiveDate::from_ymd_opt(2020, 4, 17).unwrap();
+        let days: Vec<CalendarDay> = calendar.get_days(&from, &to).await.expect("failed");
+
+        let time_off = find_time_off(&days);
+
+        assert_eq!(
+            time_off.long_weekends,
+            vec![LongWeekend {
+                from: NaiveDate::from_ymd_opt(2020, 4, 9).unwrap(),
+                to: NaiveDate::from_ymd_opt(2020, 4, 13).unwrap(),
+                workdays_left: 3,
+            }]
+        );
+        assert!(time_off.squeeze_days.is_empty());
+    }
+}
@@ -0,0 +1,424 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::config::{ConfigProblem, Configuration};
+use crate::config_source::ConfigSource;
+use crate::message_context::{is_known_context, CONTEXTS};
+use crate::template::{get_unknown_variables, render, Variables};
+
+// Slack truncates longer messages, see https://api.slack.com/methods/chat.postMessage#truncating
+const MAX_MESSAGE_LENGTH: usize = 4000;
+
+// Formatting characters in Slack mrkdwn that have to come in pairs
+const MRKDWN_CHARACTERS: [char; 4] = ['*', '_', '~', '`'];
+
+/// A problem in a configuration file, at a line and column when it could be found.
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    pub file: PathBuf,
+    /// 1-based line and column
+    pub position: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.position {
+            Some((line, column)) => write!(
+                f,
+                "{}:{}:{}: {}",
+                self.file.display(),
+                line,
+                column,
+                self.message
+            ),
+            None => write!(f, "{}: {}", self.file.display(), self.message),
+        }
+    }
+}
+
+struct SourceFile {
+    path: PathBuf,
+    text: String,
+}
+
+/// Checks every file of `source` and the configuration they make up together.
+pub fn validate_config(source: &ConfigSource) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut files = Vec::new();
+
+    for path in source.get_paths() {
+        match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                if let Err(error) = serde_yaml::from_str::<serde_yaml::Value>(&text) {
+                    diagnostics.push(yaml_diagnostic(&path, &error));
+                }
+                files.push(SourceFile { path, text });
+            }
+            Err(error) => diagnostics.push(Diagnostic {
+                file: path,
+                position: None,
+                message: format!("couldn't be read: {}", error),
+            }),
+        }
+    }
+    if !diagnostics.is_empty() {
+        return diagnostics;
+    }
+
+    let config = match source
+        .read_value()
+        .map_err(|error| error.to_string())
+        .and_then(|value| Configuration::parse_value(value).map_err(|error| error.to_string()))
+    {
+        Ok(config) => config,
+        Err(error) => {
+            // The base file on its own points out where the problem is, when it is there
+            let base = &files[0];
+            let diagnostic = match serde_yaml::from_str::<Configuration>(&base.text) {
+                Err(error) if error.location().is_some() => yaml_diagnostic(&base.path, &error),
+                _ => Diagnostic {
+                    file: base.path.clone(),
+                    position: None,
+                    message: error,
+                },
+            };
+            return vec![diagnostic];
+        }
+    };
+
+    let mut problems: Vec<(ConfigProblem, Option<String>)> = config
+        .validate()
+        .into_iter()
+        .map(|problem| (problem, None))
+        .collect();
+    problems.extend(check_messages(&config));
+
+    problems
+        .into_iter()
+        .map(|problem| locate_problem(&files, problem))
+        .collect()
+}
+
+/// Checks the messages that are sent to Slack.
+pub fn check_messages(config: &Configuration) -> Vec<(ConfigProblem, Option<String>)> {
+    let mut problems = Vec::new();
+
+    for pool in config.get_pools() {
+        if pool
+            .context
+            .is_some_and(|context| !is_known_context(context))
+        {
+            problems.push((
+                ConfigProblem::new(
+                    &pool.path,
+                    &format!(
+                        "must be general, a month from 1 to 12 or one of {}",
+                        CONTEXTS.join(", ")
+                    ),
+                ),
+                None,
+            ));
+        }
+
+        let mut seen = HashSet::new();
+        for message in pool.messages {
+            let mut problem = |text: String| {
+                problems.push((
+                    ConfigProblem::new(&pool.path, &text),
+                    Some(message.to_string()),
+                ))
+            };
+
+            if !seen.insert(message) {
+                problem(String::from("has a duplicate message"));
+            }
+            if message.chars().count() > MAX_MESSAGE_LENGTH {
+                problem(format!(
+                    "has a message longer than {} characters",
+                    MAX_MESSAGE_LENGTH
+                ));
+            }
+            if let Some(character) = get_unbalanced_mrkdwn(message) {
+                problem(format!("has a message with an unpaired '{}'", character));
+            }
+            for variable in get_unknown_variables(message, pool.variables) {
+                problem(format!(
+                    "has a message with unknown variable {{{{{}}}}}",
+                    variable
+                ));
+            }
+            if !has_balanced_placeholders(message) {
+                problem(String::from(
+                    "has a message with an unclosed {{placeholder}}",
+                ));
+            }
+        }
+    }
+
+    problems
+}
+
+/// The first formatting character without a pair, ignoring links, mentions and emoji.
+fn get_unbalanced_mrkdwn(message: &str) -> Option<char> {
+    // Variables like {{month_name}} are replaced before Slack sees the message
+    let message = render(message, &Variables::default());
+    let mut text = String::new();
+    let mut rest = message.as_str();
+
+    // Links and mentions like <@U142GKR27> and emoji like :palm_tree: may contain anything
+    while let Some(start) = rest.find(['<', ':']) {
+        text.push_str(&rest[..start]);
+        let closing = if rest[start..].starts_with('<') {
+            '>'
+        } else {
+            ':'
+        };
+        match rest[start + 1..].find(closing) {
+            Some(end)
+                if closing == '>'
+                    || rest[start + 1..start + 1 + end]
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "_+-".contains(c))
+                        && end > 0 =>
+            {
+                rest = &rest[start + end + 2..];
+            }
+            _ => {
+                text.push_str(&rest[start..start + 1]);
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    text.push_str(rest);
+
+    MRKDWN_CHARACTERS
+        .into_iter()
+        .find(|character| text.matches(*character).count() % 2 == 1)
+}
+
+fn has_balanced_placeholders(message: &str) -> bool {
+    let mut rest = message;
+    while let Some(start) = rest.find("{{") {
+        match rest[start + 2..].find("}}") {
+            Some(end) if !rest[start + 2..start + 2 + end].contains("{{") => {
+                rest = &rest[start + end + 4..]
+            }
+            _ => return false,
+        }
+    }
+    !rest.contains("}}")
+}
+
+fn yaml_diagnostic(path: &Path, error: &serde_yaml::Error) -> Diagnostic {
+    Diagnostic {
+        file: path.to_path_buf(),
+        position: error
+            .location()
+            .map(|location| (location.line(), location.column())),
+        message: error.to_string(),
+    }
+}
+
+// Problems are pointed out in the last file that sets the key, as that is the one that is used
+fn locate_problem(
+    files: &[SourceFile],
+    (problem, message): (ConfigProblem, Option<String>),
+) -> Diagnostic {
+    let path: Vec<&str> = problem.path.iter().map(String::as_str).collect();
+    let located = files.iter().rev().find_map(|file| {
+        let key = locate_key(&file.text, &path)?;
+        let position = message
+            .as_ref()
+            .and_then(|message| locate_message(&file.text, key, message))
+            .unwrap_or(key);
+        Some((file, position))
+    });
+
+    match located {
+        Some((file, position)) => Diagnostic {
+            file: file.path.clone(),
+            position: Some(position),
+            message: problem.to_string(),
+        },
+        None => Diagnostic {
+            file: files[0].path.clone(),
+            position: None,
+            message: problem.to_string(),
+        },
+    }
+}
+
+fn get_indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn is_key(line: &str, key: &str) -> bool {
+    let line = line.trim_start();
+    [
+        format!("{}:", key),
+        format!("\"{}\":", key),
+        format!("'{}':", key),
+    ]
+    .iter()
+    .any(|prefix| line.starts_with(prefix.as_str()))
+}
+
+/// Finds a key in block style YAML, e.g. `["time_report", "beginning", "12"]`.
+fn locate_key(text: &str, path: &[&str]) -> Option<(usize, usize)> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut from = 0;
+    let mut parent_indent = None;
+    let mut position = None;
+
+    for key in path {
+        let mut found = None;
+        for (index, line) in lines.iter().enumerate().skip(from) {
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let indent = get_indent(line);
+            if parent_indent.is_some_and(|parent| indent <= parent) {
+                break;
+            }
+            if is_key(line, key) {
+                found = Some((index, indent));
+                break;
+            }
+        }
+
+        let (index, indent) = found?;
+        from = index + 1;
+        parent_indent = Some(indent);
+        position = Some((index + 1, indent + 1));
+    }
+
+    position
+}
+
+/// Finds the last list item under the key at `key` that is `message`.
+fn locate_message(text: &str, key: (usize, usize), message: &str) -> Option<(usize, usize)> {
+    let (key_line, key_column) = key;
+    let mut position = None;
+
+    for (index, line) in text.lines().enumerate().skip(key_line) {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if get_indent(line) < key_column {
+            break;
+        }
+        // Weighted messages are written as `- text: ...` with the weight on the next line
+        let item = trimmed
+            .strip_prefix("- ")
+            .map(|item| item.strip_prefix("text: ").unwrap_or(item));
+        if let Some(item) = item {
+            if serde_yaml::from_str::<String>(item).is_ok_and(|item| item == message) {
+                position = Some((index + 1, get_indent(line) + 3));
+            }
+        }
+    }
+
+    position
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::{
+        get_unbalanced_mrkdwn, has_balanced_placeholders, locate_key, locate_message,
+        validate_config,
+    };
+    use crate::config_source::ConfigSource;
+
+    const TEXT: &str = "time_report:\n  beginning:\n    general:\n      - \"Hej\"\n      - \"Hopp\"\n      - \"Hej\"\n  end:\n    general:\n      - Slut\n";
+
+    #[test]
+    fn keys_and_messages_are_located() {
+        let key = locate_key(TEXT, &["time_report", "end", "general"]);
+        assert_eq!(key, Some((8, 5)));
+        assert_eq!(locate_key(TEXT, &["time_report", "middle"]), None);
+
+        let general = locate_key(TEXT, &["time_report", "beginning", "general"]).unwrap();
+        assert_eq!(locate_message(TEXT, general, "Hej"), Some((6, 9)));
+    }
+
+    #[test]
+    fn mrkdwn_must_be_paired() {
+        assert_eq!(get_unbalanced_mrkdwn("*Glöm inte* att _rapportera_"), None);
+        assert_eq!(
+            get_unbalanced_mrkdwn("Hej <@U1FEF7PSN>! :palm_tree: <https://a.se/b_c|länk>"),
+            None
+        );
+        assert_eq!(get_unbalanced_mrkdwn("Kl. 12:00 är det *lunch"), Some('*'));
+        assert_eq!(get_unbalanced_mrkdwn("`kod"), Some('`'));
+        assert_eq!(get_unbalanced_mrkdwn("_{{month_name}}_"), None);
+    }
+
+    #[test]
+    fn placeholders_must_be_closed() {
+        assert!(has_balanced_placeholders("Sista dagen är {{last_workday}}"));
+        assert!(!has_balanced_placeholders("Sista dagen är {{last_workday"));
+        assert!(!has_balanced_placeholders("Sista dagen är last_workday}}"));
+    }
+
+    #[test]
+    fn problems_are_reported_with_line_and_column() {
+        let path =
+            std::env::temp_dir().join(format!("joel-bot-validate-{}.yaml", std::process::id()));
+        let config = std::fs::read_to_string("config.yaml").expect("failed to read");
+        assert!(validate_config(&ConfigSource::new(PathBuf::from("config.yaml"), None)).is_empty());
+
+        let broken = config.replace("  end:\n    general:", "  end:\n    13:");
+        std::fs::write(&path, broken).expect("failed to write");
+        let diagnostics = validate_config(&ConfigSource::new(path.clone(), None));
+        std::fs::remove_file(&path).unwrap();
+
+        let messages: Vec<String> = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.clone())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "time_report.end has no general messages",
+                "time_report.end.13 must be general, a month from 1 to 12 or one of påsk, midsommar, årsslut, jul, kvartalsslut, efter_sommaren, fredag"
+            ]
+        );
+        assert!(diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.position.is_some()));
+    }
+
+    #[test]
+    fn messages_may_only_use_their_own_variables() {
+        let path = std::env::temp_dir().join(format!(
+            "joel-bot-validate-variables-{}.yaml",
+            std::process::id()
+        ));
+        let config = std::fs::read_to_string("config.yaml").expect("failed to read");
+
+        let broken = config
+            .replacen("- \"Hejsan allihopa,", "- \"{{date}} Hejsan allihopa,", 1)
+            .replacen("*{{command}}*?", "*{{command}}* {{month_name}}?", 1);
+        std::fs::write(&path, broken).expect("failed to write");
+        let diagnostics = validate_config(&ConfigSource::new(path.clone(), None));
+        std::fs::remove_file(&path).unwrap();
+
+        let messages: Vec<String> = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.clone())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "time_report.beginning.general has a message with unknown variable {{date}}",
+                "replies.unknown_command has a message with unknown variable {{month_name}}"
+            ]
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use anyhow::Result;
+use chrono::NaiveDate;
+
+use crate::last_day::{Calendar, CalendarDay, WorkingHours};
+
+/// Working days and norm hours for a single month.
+#[derive(Debug, PartialEq)]
+pub struct WorkingTimeSummary {
+    pub workdays: usize,
+    pub half_days: usize,
+    pub hours: f64,
+}
+
+pub async fn get_working_time(
+    calendar: &Calendar,
+    month: &NaiveDate,
+) -> Result<WorkingTimeSummary> {
+    let days = calendar.get_month(month).await?;
+
+    Ok(summarize(calendar, &days))
+}
+
+pub fn summarize(calendar: &Calendar, days: &[CalendarDay]) -> WorkingTimeSummary {
+    let workdays = days.iter().filter(|day| day.is_workday());
+
+    WorkingTimeSummary {
+        workdays: workdays.clone().count(),
+        half_days: workdays
+            .clone()
+            .filter(|day| day.hours == WorkingHours::Half)
+            .count(),
+        hours: workdays.map(|day| calendar.get_hours(day)).sum(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Datelike, NaiveDate, Weekday};
+
+    use super::{summarize, WorkingTimeSummary};
+    use crate::config::WorkingTime;
+    use crate::holidays::Country;
+    use crate::last_day::{Calendar, CalendarDay, WorkingHours};
+
+    #[test]
+    fn april_2020_with_a_half_day_before_first_of_may() {
+        let days: Vec<CalendarDay> = (1..=30)
+            .map(|day| {
+                let date = NaiveDate::from_ymd_opt(2020, 4, day).unwrap();
+                let hours = match (date.weekday(), day) {
+                    (Weekday::Sat | Weekday::Sun, _) => WorkingHours::Free,
+                    (_, 30) => WorkingHours::Half,
+                    _ => WorkingHours::Full,
+                };
+                CalendarDay {
+                    date,
+                    hours,
+                    holiday: None,
+                    reason: None,
+                }
+            })
+            .collect();
+
+        let calendar = Calendar::new(
+            WorkingTime {
+                hours_per_day: 7.5,
+                office_hours: Default::default(),
+                days: Vec::new(),
+                overrides: Vec::new(),
+            },
+            Country::SE,
+        );
+
+        assert_eq!(
+            summarize(&calendar, &days),
+            WorkingTimeSummary {
+                workdays: 22,
+                half_days: 1,
+                hours: 161.25,
+            }
+        );
+    }
+}